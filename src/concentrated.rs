@@ -0,0 +1,487 @@
+// inside concentrated.rs
+use std::convert::TryFrom;
+
+use crate::curve::{self, mul_div};
+
+/// Lowest tick of a concentrated-liquidity pool, at which the square root price is about 2^-32
+pub const MIN_TICK: i32 = -443_636;
+/// Highest tick of a concentrated-liquidity pool, at which the square root price is about 2^32
+pub const MAX_TICK: i32 = 443_636;
+/// Largest distance between two usable ticks of a concentrated-liquidity pool
+pub const MAX_TICK_SPACING: u16 = 16_384;
+
+/// One as a UQ64.64 fixed-point number
+const Q64: u128 = 1 << 64;
+
+/// `2^64 / sqrt(1.0001)^(2^i)`, rounded down
+const TICK_RATIOS: [u128; 20] = [
+    18_445_821_805_675_392_311,
+    18_444_899_583_751_176_498,
+    18_443_055_278_223_354_162,
+    18_439_367_220_385_604_838,
+    18_431_993_317_065_449_817,
+    18_417_254_355_718_160_513,
+    18_387_811_781_193_591_352,
+    18_329_067_761_203_520_168,
+    18_212_142_134_806_087_854,
+    17_980_523_815_641_551_639,
+    17_526_086_738_831_147_013,
+    16_651_378_430_235_024_244,
+    15_030_750_278_693_429_944,
+    12_247_334_978_882_834_399,
+    8_131_365_268_884_726_200,
+    3_584_323_654_723_342_297,
+    696_457_651_847_595_233,
+    26_294_789_957_452_057,
+    37_481_735_321_082,
+    76_158_723,
+];
+
+/// Computes `sqrt(1.0001^tick)` as a UQ64.64 fixed-point number
+pub fn sqrt_price_at_tick(tick: i32) -> Option<u128> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return None;
+    }
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio = Q64;
+    for (bit, tick_ratio) in TICK_RATIOS.iter().enumerate() {
+        if abs_tick & (1 << bit) != 0 {
+            // Never overflows: both factors are at most 2^64
+            ratio = (ratio * tick_ratio) >> 64;
+        }
+    }
+    if tick > 0 {
+        Some(u128::MAX / ratio)
+    } else {
+        Some(ratio)
+    }
+}
+
+/// Square root price of [MIN_TICK](constant.MIN_TICK.html)
+pub fn min_sqrt_price() -> u128 {
+    sqrt_price_at_tick(MIN_TICK).unwrap()
+}
+
+/// Square root price of [MAX_TICK](constant.MAX_TICK.html)
+pub fn max_sqrt_price() -> u128 {
+    sqrt_price_at_tick(MAX_TICK).unwrap()
+}
+
+/// Finds the highest tick whose square root price is at most `sqrt_price`
+pub fn tick_at_sqrt_price(sqrt_price: u128) -> Option<i32> {
+    if sqrt_price < min_sqrt_price() || sqrt_price > max_sqrt_price() {
+        return None;
+    }
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        let middle = low + (high - low + 1) / 2;
+        if sqrt_price_at_tick(middle)? <= sqrt_price {
+            low = middle;
+        } else {
+            high = middle - 1;
+        }
+    }
+    Some(low)
+}
+
+/// Whether a position may span from `tick_lower` to `tick_upper` in a pool spacing its ticks `tick_spacing` apart
+pub fn is_valid_tick_range(tick_lower: i32, tick_upper: i32, tick_spacing: u16) -> bool {
+    let tick_spacing = tick_spacing as i32;
+    tick_spacing > 0
+        && MIN_TICK <= tick_lower
+        && tick_lower < tick_upper
+        && tick_upper <= MAX_TICK
+        && tick_lower % tick_spacing == 0
+        && tick_upper % tick_spacing == 0
+}
+
+/// Computes the token A and token B backing `liquidity` between `tick_lower` and `tick_upper` while the pool
+/// trades at `sqrt_price`, in `tick_current`: only token A while the price is below the range and only token B
+/// once it is above
+pub fn amounts_for_liquidity(
+    sqrt_price: u128,
+    tick_current: i32,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u64,
+    round_up: bool,
+) -> Option<(u64, u64)> {
+    let sqrt_price_lower = sqrt_price_at_tick(tick_lower)?;
+    let sqrt_price_upper = sqrt_price_at_tick(tick_upper)?;
+    if tick_current < tick_lower {
+        Some((
+            amount_a_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?,
+            0,
+        ))
+    } else if tick_current < tick_upper {
+        Some((
+            amount_a_delta(sqrt_price, sqrt_price_upper, liquidity, round_up)?,
+            amount_b_delta(sqrt_price_lower, sqrt_price, liquidity, round_up)?,
+        ))
+    } else {
+        Some((
+            0,
+            amount_b_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?,
+        ))
+    }
+}
+
+/// Computes the square root price of `amount_b / amount_a` as a UQ64.64 fixed-point number, to 32 bits
+pub fn initial_sqrt_price(amount_a: u64, amount_b: u64) -> Option<u128> {
+    let price = ((amount_b as u128) << 64).checked_div(amount_a as u128)?;
+    Some(curve::sqrt(price) << 32)
+}
+
+/// Computes the prices of token A in token B and of token B in token A at `sqrt_price`, as UQ64.64
+/// fixed-point numbers
+pub fn spot_prices(sqrt_price: u128) -> (Option<u128>, Option<u128>) {
+    let price_a = mul_div(sqrt_price, sqrt_price, Q64, false);
+    let price_b = price_a.and_then(|price_a| mul_div(Q64, Q64, price_a, false));
+    (price_a, price_b)
+}
+
+/// Reserves of a constant-product pool trading at `sqrt_price`, to measure the price impact of a trade
+pub fn virtual_reserves(sqrt_price: u128) -> Option<(u64, u64)> {
+    let reserve_a = (1u128 << 96).checked_div(sqrt_price)?;
+    let reserve_b = sqrt_price >> 32;
+    Some((
+        u64::try_from(reserve_a).ok()?,
+        u64::try_from(reserve_b).ok()?,
+    ))
+}
+
+/// Computes the token A held by `liquidity` between two square root prices,
+/// `liquidity * (upper - lower) / (upper * lower)`
+pub fn amount_a_delta(
+    sqrt_price_a: u128,
+    sqrt_price_b: u128,
+    liquidity: u64,
+    round_up: bool,
+) -> Option<u64> {
+    let (lower, upper) = (
+        sqrt_price_a.min(sqrt_price_b),
+        sqrt_price_a.max(sqrt_price_b),
+    );
+    if lower == 0 {
+        return None;
+    }
+    let numerator = (liquidity as u128) << 64;
+    let amount = mul_div(numerator, upper - lower, upper, round_up)?;
+    let amount = if round_up {
+        amount.checked_add(lower - 1)? / lower
+    } else {
+        amount / lower
+    };
+
+    u64::try_from(amount).ok()
+}
+
+/// Computes the token B held by `liquidity` between two square root prices, `liquidity * (upper - lower)`
+pub fn amount_b_delta(
+    sqrt_price_a: u128,
+    sqrt_price_b: u128,
+    liquidity: u64,
+    round_up: bool,
+) -> Option<u64> {
+    let (lower, upper) = (
+        sqrt_price_a.min(sqrt_price_b),
+        sqrt_price_a.max(sqrt_price_b),
+    );
+    let amount = mul_div(liquidity as u128, upper - lower, Q64, round_up)?;
+
+    u64::try_from(amount).ok()
+}
+
+/// Computes the square root price reached by adding or removing `amount` token A, rounded up
+fn next_sqrt_price_from_amount_a(
+    sqrt_price: u128,
+    liquidity: u64,
+    amount: u64,
+    add: bool,
+) -> Option<u128> {
+    if amount == 0 {
+        return Some(sqrt_price);
+    }
+    let numerator = (liquidity as u128) << 64;
+    let product = (amount as u128).checked_mul(sqrt_price);
+    if add {
+        if let Some(denominator) = product.and_then(|product| numerator.checked_add(product)) {
+            return mul_div(numerator, sqrt_price, denominator, true);
+        }
+        let denominator = (numerator / sqrt_price).checked_add(amount as u128)?;
+        numerator
+            .checked_add(denominator - 1)
+            .map(|numerator| numerator / denominator)
+    } else {
+        let denominator = numerator.checked_sub(product?)?;
+        if denominator == 0 {
+            return None;
+        }
+        mul_div(numerator, sqrt_price, denominator, true)
+    }
+}
+
+/// Computes the square root price reached by adding or removing `amount` token B, rounded down
+fn next_sqrt_price_from_amount_b(
+    sqrt_price: u128,
+    liquidity: u64,
+    amount: u64,
+    add: bool,
+) -> Option<u128> {
+    let liquidity = liquidity as u128;
+    if liquidity == 0 {
+        return None;
+    }
+    let numerator = (amount as u128) << 64;
+    if add {
+        sqrt_price.checked_add(numerator / liquidity)
+    } else {
+        let quotient = numerator.checked_add(liquidity - 1)? / liquidity;
+        sqrt_price
+            .checked_sub(quotient)
+            .filter(|sqrt_price| *sqrt_price > 0)
+    }
+}
+
+/// The outcome of trading within a single range of constant liquidity
+pub struct SwapStep {
+    pub sqrt_price: u128,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+}
+
+/// Trades `amount_remaining` (input if `exact_in`, output otherwise) against `liquidity` from `sqrt_price`
+/// towards `sqrt_price_target`, stopping there if the amount is not used up on the way. The trade sells
+/// token A when the target is below the current price. The fee of `fee_numerator / fee_denominator` is
+/// charged on the input, and every amount is rounded in the pool's favor.
+pub fn compute_swap_step(
+    sqrt_price: u128,
+    sqrt_price_target: u128,
+    liquidity: u64,
+    amount_remaining: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+    exact_in: bool,
+) -> Option<SwapStep> {
+    let a_to_b = sqrt_price >= sqrt_price_target;
+
+    let next_sqrt_price = if exact_in {
+        let amount_remaining_less_fee =
+            curve::amount_without_fee(amount_remaining, fee_numerator, fee_denominator)?;
+        let amount_in_to_target = if a_to_b {
+            amount_a_delta(sqrt_price_target, sqrt_price, liquidity, true)
+        } else {
+            amount_b_delta(sqrt_price, sqrt_price_target, liquidity, true)
+        };
+        match amount_in_to_target {
+            Some(amount_in) if amount_remaining_less_fee >= amount_in => sqrt_price_target,
+            _ if a_to_b => next_sqrt_price_from_amount_a(
+                sqrt_price,
+                liquidity,
+                amount_remaining_less_fee,
+                true,
+            )?,
+            _ => next_sqrt_price_from_amount_b(
+                sqrt_price,
+                liquidity,
+                amount_remaining_less_fee,
+                true,
+            )?,
+        }
+    } else {
+        let amount_out_to_target = if a_to_b {
+            amount_b_delta(sqrt_price_target, sqrt_price, liquidity, false)
+        } else {
+            amount_a_delta(sqrt_price, sqrt_price_target, liquidity, false)
+        };
+        match amount_out_to_target {
+            Some(amount_out) if amount_remaining >= amount_out => sqrt_price_target,
+            _ if a_to_b => {
+                next_sqrt_price_from_amount_b(sqrt_price, liquidity, amount_remaining, false)?
+            }
+            _ => next_sqrt_price_from_amount_a(sqrt_price, liquidity, amount_remaining, false)?,
+        }
+    };
+
+    let (amount_in, mut amount_out) = if a_to_b {
+        (
+            amount_a_delta(next_sqrt_price, sqrt_price, liquidity, true)?,
+            amount_b_delta(next_sqrt_price, sqrt_price, liquidity, false)?,
+        )
+    } else {
+        (
+            amount_b_delta(sqrt_price, next_sqrt_price, liquidity, true)?,
+            amount_a_delta(sqrt_price, next_sqrt_price, liquidity, false)?,
+        )
+    };
+    if !exact_in {
+        amount_out = amount_out.min(amount_remaining);
+    }
+    let fee = if exact_in && next_sqrt_price != sqrt_price_target {
+        // The price stopped short of the target: whatever is left of the input is the fee
+        amount_remaining.checked_sub(amount_in)?
+    } else {
+        curve::amount_with_fee(amount_in, fee_numerator, fee_denominator)? - amount_in
+    };
+
+    Some(SwapStep {
+        sqrt_price: next_sqrt_price,
+        amount_in,
+        amount_out,
+        fee,
+    })
+}
+
+/// Computes the fees earned per unit of liquidity between two ticks, given the fees earned per unit of
+/// liquidity by the whole pool and outside of each tick. Meant to wrap around like the growths it is
+/// computed from: only differences of it are meaningful.
+pub fn fee_growth_inside(
+    tick_current: i32,
+    tick_lower: i32,
+    tick_upper: i32,
+    fee_growth_global: u128,
+    fee_growth_outside_lower: u128,
+    fee_growth_outside_upper: u128,
+) -> u128 {
+    let fee_growth_below = if tick_current >= tick_lower {
+        fee_growth_outside_lower
+    } else {
+        fee_growth_global.wrapping_sub(fee_growth_outside_lower)
+    };
+    let fee_growth_above = if tick_current < tick_upper {
+        fee_growth_outside_upper
+    } else {
+        fee_growth_global.wrapping_sub(fee_growth_outside_upper)
+    };
+    fee_growth_global
+        .wrapping_sub(fee_growth_below)
+        .wrapping_sub(fee_growth_above)
+}
+
+/// Computes the fees earned by `liquidity` over a growth of `fee_growth` per unit of liquidity, rounded down
+pub fn fees_earned(fee_growth: u128, liquidity: u64) -> Option<u64> {
+    u64::try_from(mul_div(fee_growth, liquidity as u128, Q64, false)?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sqrt_price_at_tick_bounds() {
+        assert_eq!(sqrt_price_at_tick(0), Some(Q64));
+        assert_eq!(sqrt_price_at_tick(MIN_TICK - 1), None);
+        assert_eq!(sqrt_price_at_tick(MAX_TICK + 1), None);
+        assert!(min_sqrt_price() > 0);
+        // sqrt(1.0001^tick) * sqrt(1.0001^-tick) is one, to the precision of the ratios
+        for tick in [1, 100, MAX_TICK] {
+            let product = mul_div(
+                sqrt_price_at_tick(tick).unwrap(),
+                sqrt_price_at_tick(-tick).unwrap(),
+                Q64,
+                false,
+            )
+            .unwrap();
+            assert!(product.abs_diff(Q64) < Q64 / 1_000_000_000);
+        }
+    }
+
+    #[test]
+    fn tick_at_sqrt_price_at_the_tick_bounds() {
+        assert_eq!(tick_at_sqrt_price(min_sqrt_price()), Some(MIN_TICK));
+        assert_eq!(tick_at_sqrt_price(max_sqrt_price()), Some(MAX_TICK));
+        assert_eq!(tick_at_sqrt_price(min_sqrt_price() - 1), None);
+        assert_eq!(tick_at_sqrt_price(max_sqrt_price() + 1), None);
+        assert_eq!(tick_at_sqrt_price(0), None);
+        assert_eq!(tick_at_sqrt_price(u128::MAX), None);
+    }
+
+    #[test]
+    fn tick_at_sqrt_price_inverts_sqrt_price_at_tick() {
+        for tick in [
+            MIN_TICK,
+            MIN_TICK + 1,
+            -100,
+            -1,
+            0,
+            1,
+            100,
+            MAX_TICK - 1,
+            MAX_TICK,
+        ] {
+            let sqrt_price = sqrt_price_at_tick(tick).unwrap();
+            assert_eq!(tick_at_sqrt_price(sqrt_price), Some(tick));
+            if tick > MIN_TICK {
+                assert_eq!(tick_at_sqrt_price(sqrt_price - 1), Some(tick - 1));
+            }
+        }
+    }
+
+    #[test]
+    fn swap_step_exact_in_stops_short_of_the_target() {
+        let target = sqrt_price_at_tick(-100).unwrap();
+        let step = compute_swap_step(Q64, target, 1_000_000, 1_000, 3, 1_000, true).unwrap();
+        assert!(step.sqrt_price > target && step.sqrt_price < Q64);
+        assert_eq!(step.amount_in + step.fee, 1_000);
+        assert!(step.amount_out < step.amount_in);
+    }
+
+    #[test]
+    fn swap_step_exact_in_reaches_the_target() {
+        let target = sqrt_price_at_tick(100).unwrap();
+        let step = compute_swap_step(Q64, target, 1_000_000, u64::MAX, 3, 1_000, true).unwrap();
+        assert_eq!(step.sqrt_price, target);
+        assert_eq!(
+            step.amount_in,
+            amount_b_delta(Q64, target, 1_000_000, true).unwrap()
+        );
+        assert_eq!(
+            step.amount_out,
+            amount_a_delta(Q64, target, 1_000_000, false).unwrap()
+        );
+        assert!(step.fee * 1_000 >= step.amount_in * 3);
+    }
+
+    #[test]
+    fn swap_step_exact_out_pays_out_no_more_than_asked() {
+        let target = sqrt_price_at_tick(-100).unwrap();
+        let step = compute_swap_step(Q64, target, 1_000_000, 1_000, 3, 1_000, false).unwrap();
+        assert!(step.sqrt_price > target && step.sqrt_price < Q64);
+        assert_eq!(step.amount_out, 1_000);
+        assert!(step.amount_in > 1_000);
+        assert!(step.fee * 1_000 >= step.amount_in * 3);
+    }
+
+    #[test]
+    fn swap_step_without_liquidity_moves_to_the_target_for_free() {
+        for exact_in in [true, false] {
+            let step = compute_swap_step(
+                max_sqrt_price(),
+                min_sqrt_price(),
+                0,
+                1_000,
+                3,
+                1_000,
+                exact_in,
+            )
+            .unwrap();
+            assert_eq!(step.sqrt_price, min_sqrt_price());
+            assert_eq!((step.amount_in, step.amount_out, step.fee), (0, 0, 0));
+        }
+    }
+
+    #[test]
+    fn swap_step_across_the_whole_tick_range() {
+        for (from, to) in [
+            (max_sqrt_price(), min_sqrt_price()),
+            (min_sqrt_price(), max_sqrt_price()),
+        ] {
+            let step = compute_swap_step(from, to, 1_000_000, u64::MAX, 3, 1_000, true).unwrap();
+            assert_eq!(step.sqrt_price, to);
+            assert!(step.amount_out < step.amount_in);
+            // Both tokens held over the whole range overflow a u64 at the largest liquidity
+            assert!(compute_swap_step(from, to, u64::MAX, u64::MAX, 3, 1_000, true).is_none());
+        }
+    }
+}
@@ -0,0 +1,836 @@
+// inside curve.rs
+use std::convert::TryFrom;
+
+use solana_program::program_error::ProgramError;
+
+/// The invariant a pool prices trades against
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CurveType {
+    /// `reserve_a * reserve_b = k`
+    ConstantProduct,
+    /// Curve's StableSwap invariant, much deeper than the constant product around a 1:1 price
+    StableSwap,
+    /// Balancer's weighted product `reserve_a^weight_a * reserve_b^weight_b = k`
+    Weighted,
+    /// A weighted product whose weights move over a token sale, see
+    /// [AMM::weight_a_at](../state/struct.AMM.html#method.weight_a_at)
+    LiquidityBootstrapping,
+    /// Uniswap v3's concentrated liquidity: positions provide `reserve_a * reserve_b = liquidity^2`
+    /// liquidity between two ticks only, see the [concentrated](../concentrated/index.html) module
+    Concentrated,
+    /// DODO's proactive market maker: trades at an oracle's price while the reserves are at their targets
+    /// and slips away from it as they leave them, see the [pmm](../pmm/index.html) module
+    ProactiveMarketMaker,
+}
+
+impl TryFrom<u8> for CurveType {
+    type Error = ProgramError;
+
+    fn try_from(curve_type: u8) -> Result<Self, Self::Error> {
+        match curve_type {
+            0 => Ok(CurveType::ConstantProduct),
+            1 => Ok(CurveType::StableSwap),
+            2 => Ok(CurveType::Weighted),
+            3 => Ok(CurveType::LiquidityBootstrapping),
+            4 => Ok(CurveType::Concentrated),
+            5 => Ok(CurveType::ProactiveMarketMaker),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+}
+
+/// Computes how many output tokens a trade of `amount_in` receives against the
+/// constant-product curve `reserve_in * reserve_out = k`, rounded down in the pool's favor.
+pub fn swap_output(amount_in: u64, reserve_in: u64, reserve_out: u64) -> Option<u64> {
+    let amount_in = amount_in as u128;
+    let reserve_in = reserve_in as u128;
+    let reserve_out = reserve_out as u128;
+
+    let numerator = reserve_out.checked_mul(amount_in)?;
+    let denominator = reserve_in.checked_add(amount_in)?;
+    let amount_out = numerator.checked_div(denominator)?;
+
+    u64::try_from(amount_out).ok()
+}
+
+/// Computes how many input tokens are needed to take exactly `amount_out` out of the pool,
+/// the inverse of [swap_output](fn.swap_output.html) rounded up in the pool's favor.
+/// `amount_out` must be strictly less than `reserve_out`.
+pub fn swap_input(amount_out: u64, reserve_in: u64, reserve_out: u64) -> Option<u64> {
+    let amount_out = amount_out as u128;
+    let reserve_in = reserve_in as u128;
+    let reserve_out = reserve_out as u128;
+
+    let numerator = reserve_in.checked_mul(amount_out)?;
+    let denominator = reserve_out.checked_sub(amount_out)?;
+    if denominator == 0 {
+        return None;
+    }
+    let amount_in = numerator
+        .checked_add(denominator - 1)?
+        .checked_div(denominator)?;
+
+    u64::try_from(amount_in).ok()
+}
+
+/// Computes the part of `amount_in` left to trade against the curve once the trading fee of
+/// `fee_numerator / fee_denominator` is taken out, rounded down in the pool's favor.
+pub fn amount_without_fee(amount_in: u64, fee_numerator: u64, fee_denominator: u64) -> Option<u64> {
+    let amount_in = amount_in as u128;
+    let fee_numerator = fee_numerator as u128;
+    let fee_denominator = fee_denominator as u128;
+
+    let amount = amount_in
+        .checked_mul(fee_denominator.checked_sub(fee_numerator)?)?
+        .checked_div(fee_denominator)?;
+
+    u64::try_from(amount).ok()
+}
+
+/// Computes the `fee_numerator / fee_denominator` share of `amount`, rounded down
+pub fn fee_amount(amount: u64, fee_numerator: u64, fee_denominator: u64) -> Option<u64> {
+    let fee = (amount as u128)
+        .checked_mul(fee_numerator as u128)?
+        .checked_div(fee_denominator as u128)?;
+
+    u64::try_from(fee).ok()
+}
+
+/// Computes the input that leaves `amount` to trade against the curve once the trading fee is
+/// taken out, the inverse of [amount_without_fee](fn.amount_without_fee.html) rounded up in the pool's favor.
+pub fn amount_with_fee(amount: u64, fee_numerator: u64, fee_denominator: u64) -> Option<u64> {
+    let amount = amount as u128;
+    let fee_numerator = fee_numerator as u128;
+    let fee_denominator = fee_denominator as u128;
+
+    let divisor = fee_denominator.checked_sub(fee_numerator)?;
+    if divisor == 0 {
+        return None;
+    }
+    let amount_in = amount
+        .checked_mul(fee_denominator)?
+        .checked_add(divisor - 1)?
+        .checked_div(divisor)?;
+
+    u64::try_from(amount_in).ok()
+}
+
+/// Computes the pool invariant `k = reserve_a * reserve_b`
+pub fn invariant(reserve_a: u64, reserve_b: u64) -> u128 {
+    reserve_a as u128 * reserve_b as u128
+}
+
+/// Computes the price of one `base` token in `quote` tokens implied by the reserves and their weights,
+/// `(reserve_quote / weight_quote) / (reserve_base / weight_base)`, as a UQ64.64 fixed-point number.
+/// Returns `None` while `reserve_base` is empty.
+pub fn spot_price(
+    reserve_base: u64,
+    weight_base: u64,
+    reserve_quote: u64,
+    weight_quote: u64,
+) -> Option<u128> {
+    let price = ((reserve_quote as u128) << 64).checked_div(reserve_base as u128)?;
+    let (weight_base, weight_quote) = (weight_base as u128, weight_quote as u128);
+    (price / weight_quote)
+        .checked_mul(weight_base)?
+        .checked_add((price % weight_quote).checked_mul(weight_base)? / weight_quote)
+}
+
+/// Computes how much worse than the spot price `(reserve_out / weight_out) / (reserve_in / weight_in)`
+/// a trade of `amount_in` for `amount_out` executes, in basis points rounded up.
+pub fn price_impact_bps(
+    amount_in: u64,
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    weight_in: u64,
+    weight_out: u64,
+) -> Option<u64> {
+    let divisor = gcd(weight_in, weight_out);
+    let (weight_in, weight_out) = (
+        weight_in.checked_div(divisor)?,
+        weight_out.checked_div(divisor)?,
+    );
+    let spot_amount_out = (amount_in as u128)
+        .checked_mul(reserve_out as u128)?
+        .checked_mul(weight_in as u128)?;
+    let executed_amount_out = (amount_out as u128)
+        .checked_mul(reserve_in as u128)?
+        .checked_mul(weight_out as u128)?;
+    if spot_amount_out == 0 {
+        return None;
+    }
+    let impact = spot_amount_out
+        .saturating_sub(executed_amount_out)
+        .checked_mul(10_000)?
+        .checked_add(spot_amount_out - 1)?
+        .checked_div(spot_amount_out)?;
+
+    u64::try_from(impact).ok()
+}
+
+/// LP tokens permanently locked by the first deposit so the share price can never be reset to zero.
+/// It is never minted; instead it is added to the mint supply whenever shares are priced.
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+/// Computes the LP tokens issued for seeding an empty pool with `amount_a` and `amount_b`,
+/// `sqrt(amount_a * amount_b)` minus [MINIMUM_LIQUIDITY](constant.MINIMUM_LIQUIDITY.html).
+pub fn initial_liquidity(amount_a: u64, amount_b: u64) -> Option<u64> {
+    let liquidity = sqrt(invariant(amount_a, amount_b));
+    let liquidity = u64::try_from(liquidity).ok()?;
+    Some(liquidity.saturating_sub(MINIMUM_LIQUIDITY))
+}
+
+/// Computes the LP tokens issued for a deposit of `amount_a` and `amount_b` into a seeded pool,
+/// the smaller of their two shares of the current reserves.
+pub fn deposit_liquidity(
+    amount_a: u64,
+    amount_b: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+    supply: u64,
+) -> Option<u64> {
+    let supply = supply.checked_add(MINIMUM_LIQUIDITY)? as u128;
+    let share_a = (amount_a as u128)
+        .checked_mul(supply)?
+        .checked_div(reserve_a as u128)?;
+    let share_b = (amount_b as u128)
+        .checked_mul(supply)?
+        .checked_div(reserve_b as u128)?;
+
+    u64::try_from(share_a.min(share_b)).ok()
+}
+
+/// Computes the tokens that have to be deposited to receive `liquidity` LP tokens from an
+/// already seeded pool, rounded up in the pool's favor.
+pub fn deposit_amounts(
+    liquidity: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+    supply: u64,
+) -> Option<(u64, u64)> {
+    let supply = supply.checked_add(MINIMUM_LIQUIDITY)? as u128;
+    let share_ceil = |reserve: u64| -> Option<u64> {
+        let amount = (liquidity as u128)
+            .checked_mul(reserve as u128)?
+            .checked_add(supply - 1)?
+            .checked_div(supply)?;
+        u64::try_from(amount).ok()
+    };
+
+    Some((share_ceil(reserve_a)?, share_ceil(reserve_b)?))
+}
+
+/// Computes the pro-rata token A and token B paid out for redeeming `liquidity` LP tokens,
+/// rounded down in the pool's favor.
+pub fn withdraw_amounts(
+    liquidity: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+    supply: u64,
+) -> Option<(u64, u64)> {
+    let supply = supply.checked_add(MINIMUM_LIQUIDITY)? as u128;
+    let share_floor = |reserve: u64| -> Option<u64> {
+        let amount = (liquidity as u128)
+            .checked_mul(reserve as u128)?
+            .checked_div(supply)?;
+        u64::try_from(amount).ok()
+    };
+
+    Some((share_floor(reserve_a)?, share_floor(reserve_b)?))
+}
+
+/// Greatest common divisor
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let remainder = a % b;
+        a = b;
+        b = remainder;
+    }
+    a
+}
+
+/// Integer square root, rounded down
+pub(crate) fn sqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+    let mut x = value;
+    let mut y = x / 2 + 1;
+    while y < x {
+        x = y;
+        y = (x + value / x) / 2;
+    }
+    x
+}
+
+/// Computes `a * b / denominator` through a 256-bit intermediate product, rounded down or up
+pub fn mul_div(a: u128, b: u128, denominator: u128, round_up: bool) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+    let (a_high, a_low) = (a >> 64, a & u64::MAX as u128);
+    let (b_high, b_low) = (b >> 64, b & u64::MAX as u128);
+    let (middle, middle_carry) = (a_low * b_high).overflowing_add(a_high * b_low);
+    let (low, low_carry) = (a_low * b_low).overflowing_add(middle << 64);
+    let high =
+        a_high * b_high + (middle >> 64) + ((middle_carry as u128) << 64) + low_carry as u128;
+    if high >= denominator {
+        return None;
+    }
+
+    let (quotient, remainder) = if high == 0 {
+        (low / denominator, low % denominator)
+    } else {
+        // Long division of the 256-bit product, one bit at a time
+        let mut quotient = 0u128;
+        let mut remainder = high;
+        for bit in (0..128).rev() {
+            let carry = remainder >> 127;
+            remainder = (remainder << 1) | ((low >> bit) & 1);
+            quotient <<= 1;
+            if carry == 1 || remainder >= denominator {
+                remainder = remainder.wrapping_sub(denominator);
+                quotient |= 1;
+            }
+        }
+        (quotient, remainder)
+    };
+    if round_up && remainder > 0 {
+        quotient.checked_add(1)
+    } else {
+        Some(quotient)
+    }
+}
+
+/// Smallest amplification coefficient of a StableSwap pool, at which it trades like a constant product
+pub const MIN_AMPLIFICATION: u64 = 1;
+/// Largest amplification coefficient of a StableSwap pool
+pub const MAX_AMPLIFICATION: u64 = 1_000_000;
+/// Shortest ramp of the amplification coefficient, in seconds
+pub const MIN_RAMP_DURATION: i64 = 86_400;
+/// Largest factor by which a single ramp may raise or lower the amplification coefficient
+pub const MAX_AMPLIFICATION_CHANGE: u64 = 10;
+
+const N_COINS: u128 = 2;
+const MAX_ITERATIONS: usize = 255;
+
+/// Computes the StableSwap invariant `D` of the reserves, solving
+/// `A * n^n * (x + y) + D = A * n^n * D + D^(n + 1) / (n^n * x * y)` for `n = 2` by Newton's method.
+/// `D` is only found to within 1, and is `None` if either reserve is empty.
+pub fn stable_invariant(amplification: u64, reserve_a: u64, reserve_b: u64) -> Option<u128> {
+    let reserve_a = reserve_a as u128;
+    let reserve_b = reserve_b as u128;
+    let sum = reserve_a.checked_add(reserve_b)?;
+    let leverage = (amplification as u128).checked_mul(N_COINS)?;
+
+    let mut invariant = sum;
+    for _ in 0..MAX_ITERATIONS {
+        let invariant_product = invariant
+            .checked_mul(invariant)?
+            .checked_div(reserve_a.checked_mul(N_COINS)?)?
+            .checked_mul(invariant)?
+            .checked_div(reserve_b.checked_mul(N_COINS)?)?;
+        let previous_invariant = invariant;
+        let numerator = leverage
+            .checked_mul(sum)?
+            .checked_add(invariant_product.checked_mul(N_COINS)?)?
+            .checked_mul(invariant)?;
+        let denominator = leverage
+            .checked_sub(1)?
+            .checked_mul(invariant)?
+            .checked_add((N_COINS + 1).checked_mul(invariant_product)?)?;
+        invariant = numerator.checked_div(denominator)?;
+        if invariant.abs_diff(previous_invariant) <= 1 {
+            return Some(invariant);
+        }
+    }
+    None
+}
+
+/// Computes the reserve that keeps the StableSwap invariant at `invariant` once the other reserve is
+/// `other_reserve`, to within 1.
+fn stable_reserve(amplification: u64, other_reserve: u128, invariant: u128) -> Option<u128> {
+    let leverage = (amplification as u128).checked_mul(N_COINS)?;
+    let c = invariant
+        .checked_mul(invariant)?
+        .checked_div(other_reserve.checked_mul(N_COINS)?)?
+        .checked_mul(invariant)?
+        .checked_div(leverage.checked_mul(N_COINS)?)?;
+    let b = other_reserve.checked_add(invariant.checked_div(leverage)?)?;
+
+    let mut reserve = invariant;
+    for _ in 0..MAX_ITERATIONS {
+        let previous_reserve = reserve;
+        reserve = reserve.checked_mul(reserve)?.checked_add(c)?.checked_div(
+            reserve
+                .checked_mul(2)?
+                .checked_add(b)?
+                .checked_sub(invariant)?,
+        )?;
+        if reserve.abs_diff(previous_reserve) <= 1 {
+            return Some(reserve);
+        }
+    }
+    None
+}
+
+/// Computes how many output tokens a trade of `amount_in` receives against the StableSwap invariant,
+/// rounded down by one more token than the invariant's precision in the pool's favor.
+///
+/// On a lopsided pool, the error of `D` moves the reserves it solves for by more than one token, so the
+/// trade is measured from the output reserve solved for before the trade whenever that one is lower.
+pub fn stable_swap_output(
+    amplification: u64,
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Option<u64> {
+    let invariant = stable_invariant(amplification, reserve_in, reserve_out)?;
+    let solved_reserve_out = stable_reserve(amplification, reserve_in as u128, invariant)?;
+    let new_reserve_in = (reserve_in as u128).checked_add(amount_in as u128)?;
+    let new_reserve_out = stable_reserve(amplification, new_reserve_in, invariant)?;
+    let amount_out = (reserve_out as u128)
+        .min(solved_reserve_out)
+        .saturating_sub(new_reserve_out)
+        .saturating_sub(1);
+
+    u64::try_from(amount_out).ok()
+}
+
+/// Computes how many input tokens are needed to take exactly `amount_out` out of a StableSwap pool,
+/// the inverse of [stable_swap_output](fn.stable_swap_output.html) rounded up in the pool's favor.
+/// `amount_out` must be strictly less than `reserve_out`.
+pub fn stable_swap_input(
+    amplification: u64,
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Option<u64> {
+    let invariant = stable_invariant(amplification, reserve_in, reserve_out)?;
+    let solved_reserve_in = stable_reserve(amplification, reserve_out as u128, invariant)?;
+    let new_reserve_out = reserve_out.checked_sub(amount_out)? as u128;
+    if new_reserve_out == 0 {
+        return None;
+    }
+    let new_reserve_in = stable_reserve(amplification, new_reserve_out, invariant)?;
+    let amount_in = new_reserve_in
+        .checked_sub((reserve_in as u128).min(solved_reserve_in))?
+        .checked_add(1)?;
+
+    u64::try_from(amount_in).ok()
+}
+
+/// Sum of the two token weights of a pool. Curves other than the weighted product weigh both tokens
+/// `WEIGHT_ONE / 2`.
+pub const WEIGHT_ONE: u64 = 1_000_000;
+/// Smallest token weight of a weighted pool
+pub const MIN_WEIGHT: u64 = 20_000;
+
+/// One in the 18-decimal fixed-point numbers of the weighted product math
+const FIXED_ONE: u128 = 1_000_000_000_000_000_000;
+/// `ln(2)` to 18 decimals
+const LN_2: u128 = 693_147_180_559_945_309;
+/// Upper bound of the relative error of [pow](fn.pow.html), to 18 decimals. Powers are padded by it in the pool's favor.
+pub const MAX_POW_RELATIVE_ERROR: u128 = 10_000;
+
+/// Computes `log2(x)` of the fixed-point number `x`, to within a few hundred units of the last decimal
+fn log2(x: u128) -> Option<i128> {
+    if x == 0 {
+        return None;
+    }
+    let mut integer: i128 = 0;
+    let mut y = x;
+    while y >= 2 * FIXED_ONE {
+        y >>= 1;
+        integer += 1;
+    }
+    while y < FIXED_ONE {
+        y <<= 1;
+        integer -= 1;
+    }
+
+    // y is now in [1, 2): each squaring yields the next binary digit of its logarithm
+    let mut result = integer * FIXED_ONE as i128;
+    let mut digit = FIXED_ONE / 2;
+    while digit > 0 {
+        y = y * y / FIXED_ONE;
+        if y >= 2 * FIXED_ONE {
+            y >>= 1;
+            result += digit as i128;
+        }
+        digit >>= 1;
+    }
+    Some(result)
+}
+
+/// Computes `2^x` of the fixed-point number `x`, rounded down
+fn exp2(x: i128) -> Option<u128> {
+    let integer = x.div_euclid(FIXED_ONE as i128);
+    let fraction = x.rem_euclid(FIXED_ONE as i128) as u128;
+
+    // 2^fraction = e^(fraction * ln 2), summed as a Taylor series
+    let exponent = fraction * LN_2 / FIXED_ONE;
+    let mut term = FIXED_ONE;
+    let mut sum = FIXED_ONE;
+    let mut n = 1;
+    while term > 0 {
+        term = term * exponent / FIXED_ONE / n;
+        sum += term;
+        n += 1;
+    }
+
+    if integer >= 0 {
+        let shift = u32::try_from(integer).ok()?;
+        if shift >= sum.leading_zeros() {
+            return None;
+        }
+        Some(sum << shift)
+    } else {
+        let shift = u32::try_from(integer.unsigned_abs()).ok();
+        Some(shift.and_then(|shift| sum.checked_shr(shift)).unwrap_or(0))
+    }
+}
+
+/// Computes `base^exponent` of two fixed-point numbers, rounded up by
+/// [MAX_POW_RELATIVE_ERROR](constant.MAX_POW_RELATIVE_ERROR.html)
+fn pow_up(base: u128, exponent: u128) -> Option<u128> {
+    let logarithm = log2(base)?;
+    let exponent = i128::try_from(exponent).ok()?;
+    let one = FIXED_ONE as i128;
+    let product = logarithm
+        .div_euclid(one)
+        .checked_mul(exponent)?
+        .checked_add(logarithm.rem_euclid(one).checked_mul(exponent)? / one)?;
+    let power = exp2(product)?;
+    power
+        .checked_add(power.checked_mul(MAX_POW_RELATIVE_ERROR)? / FIXED_ONE)?
+        .checked_add(1)
+}
+
+/// Computes `numerator / denominator` as a fixed-point number, rounded up
+fn div_up(numerator: u128, denominator: u128) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+    numerator
+        .checked_mul(FIXED_ONE)?
+        .checked_add(denominator - 1)
+        .map(|numerator| numerator / denominator)
+}
+
+/// Computes how many output tokens a trade of `amount_in` receives against the weighted product,
+/// `reserve_out * (1 - (reserve_in / (reserve_in + amount_in))^(weight_in / weight_out))`, rounded down
+/// in the pool's favor.
+pub fn weighted_swap_output(
+    weight_in: u64,
+    weight_out: u64,
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Option<u64> {
+    let base = div_up(
+        reserve_in as u128,
+        (reserve_in as u128).checked_add(amount_in as u128)?,
+    )?;
+    let exponent = (weight_in as u128 * FIXED_ONE).checked_div(weight_out as u128)?;
+    let power = pow_up(base, exponent)?.min(FIXED_ONE);
+    let amount_out = (reserve_out as u128).checked_mul(FIXED_ONE - power)? / FIXED_ONE;
+
+    u64::try_from(amount_out).ok()
+}
+
+/// Computes how many input tokens are needed to take exactly `amount_out` out of a weighted pool,
+/// `reserve_in * ((reserve_out / (reserve_out - amount_out))^(weight_out / weight_in) - 1)`, rounded up
+/// in the pool's favor. `amount_out` must be strictly less than `reserve_out`.
+pub fn weighted_swap_input(
+    weight_in: u64,
+    weight_out: u64,
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Option<u64> {
+    let base = div_up(
+        reserve_out as u128,
+        reserve_out.checked_sub(amount_out)? as u128,
+    )?;
+    let exponent = div_up(weight_out as u128, weight_in as u128)?;
+    let power = pow_up(base, exponent)?;
+    let amount_in = (reserve_in as u128)
+        .checked_mul(power.checked_sub(FIXED_ONE)?)?
+        .checked_add(FIXED_ONE - 1)?
+        / FIXED_ONE;
+
+    u64::try_from(amount_in).ok()
+}
+
+/// Computes `log2(reserve_a^weight_a * reserve_b^weight_b)` with normalized weights, to 18 decimals.
+/// It only grows with the weighted product itself, but is much cheaper to check than the product.
+/// Accurate to within [WEIGHTED_INVARIANT_PRECISION](constant.WEIGHTED_INVARIANT_PRECISION.html).
+pub fn weighted_invariant(
+    weight_a: u64,
+    weight_b: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+) -> Option<u128> {
+    let log_a = u128::try_from(log2((reserve_a as u128).checked_mul(FIXED_ONE)?)?).ok()?;
+    let log_b = u128::try_from(log2((reserve_b as u128).checked_mul(FIXED_ONE)?)?).ok()?;
+    let weighted_log = log_a
+        .checked_mul(weight_a as u128)?
+        .checked_add(log_b.checked_mul(weight_b as u128)?)?;
+
+    weighted_log.checked_div(weight_a as u128 + weight_b as u128)
+}
+
+/// How far off [weighted_invariant](fn.weighted_invariant.html) may be
+pub const WEIGHTED_INVARIANT_PRECISION: u128 = 1_000;
+
+/// Computes the LP tokens issued for seeding an empty weighted pool with `amount_a` and `amount_b`,
+/// `amount_a^weight_a * amount_b^weight_b` rounded down, minus [MINIMUM_LIQUIDITY](constant.MINIMUM_LIQUIDITY.html).
+pub fn weighted_initial_liquidity(
+    weight_a: u64,
+    weight_b: u64,
+    amount_a: u64,
+    amount_b: u64,
+) -> Option<u64> {
+    let invariant = weighted_invariant(weight_a, weight_b, amount_a, amount_b)?;
+    let liquidity = exp2(i128::try_from(invariant).ok()?)? / FIXED_ONE;
+    let liquidity = u64::try_from(liquidity).ok()?;
+    Some(liquidity.saturating_sub(MINIMUM_LIQUIDITY))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AMPLIFICATIONS: [u64; 3] = [MIN_AMPLIFICATION, 100, MAX_AMPLIFICATION];
+
+    #[test]
+    fn stable_invariant_of_balanced_reserves_is_their_sum() {
+        for amplification in AMPLIFICATIONS {
+            for reserve in [1, 1_000_000, u64::MAX / 1_000_000] {
+                assert_eq!(
+                    stable_invariant(amplification, reserve, reserve),
+                    Some(2 * reserve as u128)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn stable_invariant_lies_between_product_and_sum() {
+        for amplification in AMPLIFICATIONS {
+            let invariant = stable_invariant(amplification, 1, 1_000_000).unwrap();
+            assert!(invariant >= 2 * sqrt(1_000_000));
+            assert!(invariant <= 1_000_001);
+        }
+    }
+
+    #[test]
+    fn stable_invariant_of_empty_or_huge_reserves_is_none() {
+        for amplification in AMPLIFICATIONS {
+            assert_eq!(stable_invariant(amplification, 0, 1_000_000), None);
+            assert_eq!(stable_invariant(amplification, 1_000_000, 0), None);
+            assert_eq!(stable_invariant(amplification, u64::MAX, u64::MAX), None);
+        }
+    }
+
+    #[test]
+    fn stable_reserve_recovers_balanced_reserves() {
+        for amplification in AMPLIFICATIONS {
+            let invariant = stable_invariant(amplification, 1_000_000, 1_000_000).unwrap();
+            assert_eq!(
+                stable_reserve(amplification, 1_000_000, invariant),
+                Some(1_000_000)
+            );
+        }
+    }
+
+    #[test]
+    fn stable_swap_of_nothing_pays_nothing_on_lopsided_pools() {
+        for amplification in AMPLIFICATIONS {
+            assert_eq!(stable_swap_output(amplification, 0, 1, 1_000_000), Some(0));
+            assert_eq!(
+                stable_swap_output(amplification, 0, 1_000_000, 1_000_000),
+                Some(0)
+            );
+        }
+    }
+
+    #[test]
+    fn stable_swap_beats_constant_product_on_balanced_pools() {
+        for amplification in AMPLIFICATIONS {
+            let amount_out =
+                stable_swap_output(amplification, 100_000, 1_000_000, 1_000_000).unwrap();
+            assert!(amount_out >= swap_output(100_000, 1_000_000, 1_000_000).unwrap());
+            assert!(amount_out < 100_000);
+        }
+    }
+
+    #[test]
+    fn stable_swap_input_cannot_drain_the_pool() {
+        for amplification in AMPLIFICATIONS {
+            assert_eq!(
+                stable_swap_input(amplification, 1_000_000, 1_000_000, 1_000_000),
+                None
+            );
+            assert_eq!(
+                stable_swap_input(amplification, 1_000_001, 1_000_000, 1_000_000),
+                None
+            );
+            assert!(stable_swap_input(amplification, 1_000, 1_000_000, 1_000_000).unwrap() > 1_000);
+        }
+    }
+
+    #[test]
+    fn stable_swap_round_trips_never_profit() {
+        for amplification in AMPLIFICATIONS {
+            for (reserve_in, reserve_out) in
+                [(1_000_000, 1_000_000), (1, 1_000_000), (1_000_000, 1_000)]
+            {
+                for amount in [1, 10, 999, 100_000] {
+                    if let Some(amount_out) =
+                        stable_swap_output(amplification, amount, reserve_in, reserve_out)
+                    {
+                        let amount_back = stable_swap_output(
+                            amplification,
+                            amount_out,
+                            reserve_out - amount_out,
+                            reserve_in + amount,
+                        );
+                        assert!(amount_back.unwrap_or(0) <= amount);
+                    }
+                }
+            }
+        }
+    }
+
+    const WEIGHTS: [(u64, u64); 3] = [
+        (MIN_WEIGHT, WEIGHT_ONE - MIN_WEIGHT),
+        (WEIGHT_ONE / 2, WEIGHT_ONE / 2),
+        (WEIGHT_ONE - MIN_WEIGHT, MIN_WEIGHT),
+    ];
+
+    /// `log2(x)` to within `tolerance`, in units of the last decimal
+    fn assert_log2(x: u128, expected: i128, tolerance: i128) {
+        let logarithm = log2(x).unwrap();
+        assert!(
+            (logarithm - expected).abs() <= tolerance,
+            "log2({}) = {}",
+            x,
+            logarithm
+        );
+    }
+
+    #[test]
+    fn log2_of_powers_of_two_is_exact() {
+        let one = FIXED_ONE as i128;
+        assert_eq!(log2(FIXED_ONE), Some(0));
+        assert_eq!(log2(2 * FIXED_ONE), Some(one));
+        assert_eq!(log2(FIXED_ONE / 2), Some(-one));
+        assert_eq!(log2(FIXED_ONE << 64), Some(64 * one));
+    }
+
+    #[test]
+    fn log2_of_extreme_values() {
+        assert_eq!(log2(0), None);
+        assert_log2(1, -59_794_705_707_972_522_262, 100);
+        assert_log2(3 * FIXED_ONE, 1_584_962_500_721_156_181, 100);
+        assert_log2(u128::MAX, 68_205_294_292_027_477_738, 100);
+    }
+
+    #[test]
+    fn exp2_of_extreme_values() {
+        let one = FIXED_ONE as i128;
+        assert_eq!(exp2(0), Some(FIXED_ONE));
+        assert_eq!(exp2(one), Some(2 * FIXED_ONE));
+        assert_eq!(exp2(-one), Some(FIXED_ONE / 2));
+        let sqrt_2 = exp2(one / 2).unwrap();
+        assert!((1_414_213_562_373_095_000..=1_414_213_562_373_095_048).contains(&sqrt_2));
+        assert_eq!(exp2(64 * one), Some(FIXED_ONE << 64));
+        assert_eq!(exp2(127 * one), None);
+        assert_eq!(exp2(i128::MAX), None);
+        assert_eq!(exp2(-130 * one), Some(0));
+        assert_eq!(exp2(i128::MIN), Some(0));
+    }
+
+    #[test]
+    fn pow_up_is_never_below_the_power() {
+        for base in [
+            1,
+            FIXED_ONE / 3,
+            FIXED_ONE / 2,
+            FIXED_ONE,
+            3 * FIXED_ONE,
+            1_000_000 * FIXED_ONE,
+        ] {
+            let power = pow_up(base, FIXED_ONE).unwrap();
+            assert!(power >= base);
+            assert!(power <= base + 2 * (base * MAX_POW_RELATIVE_ERROR / FIXED_ONE) + 1);
+        }
+        for base in [
+            FIXED_ONE / 3,
+            FIXED_ONE / 2,
+            3 * FIXED_ONE,
+            1_000 * FIXED_ONE,
+        ] {
+            assert!(
+                pow_up(base, 2 * FIXED_ONE).unwrap()
+                    >= mul_div(base, base, FIXED_ONE, false).unwrap()
+            );
+        }
+        assert!(pow_up(FIXED_ONE, 49 * FIXED_ONE).unwrap() >= FIXED_ONE);
+        assert!(pow_up(3 * FIXED_ONE, 0).unwrap() >= FIXED_ONE);
+        assert!(pow_up(1, 49 * FIXED_ONE).unwrap() >= 1);
+        assert_eq!(pow_up(0, FIXED_ONE), None);
+        assert_eq!(pow_up(u64::MAX as u128 * FIXED_ONE, 49 * FIXED_ONE), None);
+    }
+
+    #[test]
+    fn even_weighted_swaps_never_beat_the_constant_product() {
+        let (weight_a, weight_b) = (WEIGHT_ONE / 2, WEIGHT_ONE / 2);
+        for (reserve_in, reserve_out) in [
+            (1, u64::MAX),
+            (1_000_000, 1_000_000),
+            (u64::MAX / 2, u64::MAX / 2),
+        ] {
+            for amount in [1, 1_000, 1_000_000, u64::MAX / 2] {
+                let amount_out =
+                    weighted_swap_output(weight_a, weight_b, amount, reserve_in, reserve_out)
+                        .unwrap();
+                assert!(amount_out <= swap_output(amount, reserve_in, reserve_out).unwrap());
+                if let Some(amount_in) =
+                    weighted_swap_input(weight_a, weight_b, amount, reserve_in, reserve_out)
+                {
+                    assert!(amount_in >= swap_input(amount, reserve_in, reserve_out).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_swaps_with_extreme_weights_stay_within_the_reserves() {
+        for (weight_in, weight_out) in WEIGHTS {
+            for (reserve_in, reserve_out) in [(1, u64::MAX), (u64::MAX, 1), (1_000_000, 1_000_000)]
+            {
+                for amount in [1, 1_000, u64::MAX / 2] {
+                    if let Some(amount_out) =
+                        weighted_swap_output(weight_in, weight_out, amount, reserve_in, reserve_out)
+                    {
+                        assert!(amount_out < reserve_out);
+                    }
+                    assert_eq!(
+                        weighted_swap_input(
+                            weight_in,
+                            weight_out,
+                            reserve_out,
+                            reserve_in,
+                            reserve_out
+                        ),
+                        None
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use solana_program::{
-    account_info::AccountInfo, entrypoint, entrypoint::ProgramResult, pubkey::Pubkey
+    account_info::AccountInfo, entrypoint, entrypoint::ProgramResult, pubkey::Pubkey,
 };
 
 use crate::processor::Processor;
@@ -11,4 +11,4 @@ fn process_instruction(
     instruction_data: &[u8],
 ) -> ProgramResult {
     Processor::process(program_id, accounts, instruction_data)
-}
\ No newline at end of file
+}
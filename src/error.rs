@@ -24,10 +24,158 @@ pub enum AMMError {
     /// InvalidRatio
     #[error("Amount Overflow")]
     AmountOverflow,
+
+    /// Insufficient Liquidity
+    #[error("Insufficient Liquidity")]
+    InsufficientLiquidity,
+
+    /// Invariant Violated
+    #[error("Invariant Violated")]
+    InvariantViolated,
+
+    /// Exceeded Slippage
+    #[error("Exceeded Slippage")]
+    ExceededSlippage,
+
+    /// Pool Not Empty
+    #[error("Pool Not Empty")]
+    PoolNotEmpty,
+
+    /// Invalid Fee
+    #[error("Invalid Fee")]
+    InvalidFee,
+
+    /// Invalid Fee Authority
+    #[error("Invalid Fee Authority")]
+    InvalidFeeAuthority,
+
+    /// Invalid Mint
+    #[error("Invalid Mint")]
+    InvalidMint,
+
+    /// Missing Signature
+    #[error("Missing Signature")]
+    MissingSignature,
+
+    /// Incorrect Token Program Id
+    #[error("Incorrect Token Program Id")]
+    IncorrectTokenProgramId,
+
+    /// Invalid AMM Account Owner
+    #[error("Invalid AMM Account Owner")]
+    InvalidAMMAccountOwner,
+
+    /// Not Owned By Token Program
+    #[error("Not Owned By Token Program")]
+    NotOwnedByTokenProgram,
+
+    /// Incorrect Pool Token Account
+    #[error("Incorrect Pool Token Account")]
+    IncorrectPoolTokenAccount,
+
+    /// Incorrect Pool Mint
+    #[error("Incorrect Pool Mint")]
+    IncorrectPoolMint,
+
+    /// Invalid Pool Authority
+    #[error("Invalid Pool Authority")]
+    InvalidPoolAuthority,
+
+    /// Duplicate Accounts
+    #[error("Duplicate Accounts")]
+    DuplicateAccounts,
+
+    /// Invalid LP Supply
+    #[error("Invalid LP Supply")]
+    InvalidLPSupply,
+
+    /// Invalid Freeze Authority
+    #[error("Invalid Freeze Authority")]
+    InvalidFreezeAuthority,
+
+    /// Incorrect System Program Id
+    #[error("Incorrect System Program Id")]
+    IncorrectSystemProgramId,
+
+    /// Invalid Observations Account
+    #[error("Invalid Observations Account")]
+    InvalidObservationsAccount,
+
+    /// Insufficient Observations
+    #[error("Insufficient Observations")]
+    InsufficientObservations,
+
+    /// Invalid Amplification
+    #[error("Invalid Amplification")]
+    InvalidAmplification,
+
+    /// Invalid Ramp
+    #[error("Invalid Ramp")]
+    InvalidRamp,
+
+    /// Invalid Weight
+    #[error("Invalid Weight")]
+    InvalidWeight,
+
+    /// Sale Not Active
+    #[error("Sale Not Active")]
+    SaleNotActive,
+
+    /// Sale Not Ended
+    #[error("Sale Not Ended")]
+    SaleNotEnded,
+
+    /// Invalid Curve Type
+    #[error("Invalid Curve Type")]
+    InvalidCurveType,
+
+    /// Invalid Tick Spacing
+    #[error("Invalid Tick Spacing")]
+    InvalidTickSpacing,
+
+    /// Invalid Tick Range
+    #[error("Invalid Tick Range")]
+    InvalidTickRange,
+
+    /// Invalid Ticks Account
+    #[error("Invalid Ticks Account")]
+    InvalidTicksAccount,
+
+    /// Invalid Position Account
+    #[error("Invalid Position Account")]
+    InvalidPositionAccount,
+
+    /// Too Many Ticks
+    #[error("Too Many Ticks")]
+    TooManyTicks,
+
+    /// Invalid Slippage Factor
+    #[error("Invalid Slippage Factor")]
+    InvalidSlippageFactor,
+
+    /// Invalid Oracle Staleness
+    #[error("Invalid Oracle Staleness")]
+    InvalidOracleStaleness,
+
+    /// Invalid Oracle Account
+    #[error("Invalid Oracle Account")]
+    InvalidOracleAccount,
+
+    /// Stale Oracle Price
+    #[error("Stale Oracle Price")]
+    StaleOraclePrice,
+
+    /// Invalid Oracle Authority
+    #[error("Invalid Oracle Authority")]
+    InvalidOracleAuthority,
+
+    /// Price Deviation Exceeded
+    #[error("Price Deviation Exceeded")]
+    PriceDeviationExceeded,
 }
 
 impl From<AMMError> for ProgramError {
     fn from(e: AMMError) -> Self {
         ProgramError::Custom(e as u32)
     }
-}
\ No newline at end of file
+}
@@ -1,140 +1,625 @@
 // inside instruction.rs
-use solana_program::program_error::ProgramError;
-use std::convert::TryInto;
-use crate::error::AMMError::InvalidInstruction;
+use crate::{curve::CurveType, error::AMMError::InvalidInstruction};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+use std::convert::{TryFrom, TryInto};
+use std::mem::size_of;
 
 pub enum AMMInstruction {
-    /// Initializes the AMM
+    /// Initializes an AMM for the pair of token A and token B mints, with a trading fee of {trade_fee_numerator / trade_fee_denominator}
+    /// and a protocol fee of {protocol_fee_numerator / trade_fee_denominator}
     ///
+    /// The trading fee is taken from the input of every trade and left in the PDA's token accounts,
+    /// so it accrues to LP token holders. The protocol fee is taken on top of it and set aside
+    /// for the fee authority to withdraw.
+    ///
+    /// The PDA owning the pool's token accounts is derived from the seeds `["bravv", AMM account]`,
+    /// so every pool gets its own authority. The pool's token accounts are created by the program at the
+    /// addresses derived from the seeds `["bravv", AMM account, mint]`, owned by the PDA from the start.
+    ///
+    /// The pool is seeded in the same instruction: {initial_amount_a} and {initial_amount_b} are moved
+    /// from The Initiator Account's token accounts into the pool's token accounts,
+    /// and sqrt(initial_amount_a * initial_amount_b) LP tokens are minted to The Initiator Account, minus a
+    /// small amount that stays locked in the pool forever. The pool is therefore never empty or unpriced.
+    ///
+    /// Trades are priced against {curve_type}. A StableSwap pool takes an {amplification} coefficient
+    /// between 1 and 1,000,000, the higher the flatter its curve around a 1:1 price, and mints the
+    /// StableSwap invariant of the initial amounts instead of their geometric mean. Other curves take an
+    /// {amplification} of 0.
+    ///
+    /// A Weighted pool weighs token A {weight_a} and token B the rest of
+    /// [WEIGHT_ONE](../curve/constant.WEIGHT_ONE.html), e.g. 800,000 for an 80/20 pool, neither of
+    /// them less than 2%. Its initial amounts set the price of token A in token B to
+    /// `(initial_amount_b / weight_b) / (initial_amount_a / weight_a)`, and it mints their weighted
+    /// product. Other curves take a {weight_a} of half of `WEIGHT_ONE`.
+    ///
+    /// A LiquidityBootstrapping pool is a Weighted pool running a token sale: the weight of token A moves
+    /// linearly from {weight_a} at the unix timestamp {start_timestamp} to {end_weight_a} at {end_timestamp},
+    /// e.g. from 950,000 to 500,000, so the price of token A starts high and keeps falling unless bought,
+    /// which discourages sniping. The pool only trades during the sale, and its liquidity can only be
+    /// claimed once the sale has ended. Other curves take 0 for all three.
+    ///
+    /// A Concentrated pool has no LP tokens: liquidity is provided by positions spanning a range of ticks, see
+    /// `IncreaseLiquidity`, and the ticks that positions start or end at are {tick_spacing} apart, between 1 and 16,384.
+    /// The pool starts trading at the price of `initial_amount_b / initial_amount_a` but nothing is transferred,
+    /// and its ticks account must be created with `InitTicks` before any position is opened. Other curves take a
+    /// {tick_spacing} of 0.
+    ///
+    /// A ProactiveMarketMaker pool trades at the price published by an oracle account, see `InitOracle`, as
+    /// long as its reserves are at their targets, the initial amounts, and slips away from it as they leave
+    /// them, the steeper the higher its {slippage_factor} out of
+    /// [SLIPPAGE_FACTOR_ONE](../pmm/constant.SLIPPAGE_FACTOR_ONE.html). It refuses to trade once the oracle
+    /// price is more than {max_oracle_staleness} seconds old. Other curves take 0 for both.
+    ///
+    /// Any pool can also be guarded by an oracle account: trades are then rejected when the pool's price after
+    /// them is more than {max_price_deviation_bps} basis points away from the oracle price, or when the
+    /// oracle price is more than {max_oracle_staleness} seconds old. Unguarded pools take 0 for
+    /// {max_price_deviation_bps}, and only ProactiveMarketMaker pools take an oracle without it.
+    ///
+    /// Deposits and withdrawals are pro-rata for every other curve, which leaves the pool's price unchanged
+    /// whatever its weights and scales the invariant along with the LP supply.
     ///
     /// Accounts expected:
     ///
-    /// 0. `[signer]` The Initiator Account
-    /// 1. `[writable]` Temporary BUSD account that should be created prior to this instruction and owned by The Initiator Account
-    /// 2. `[writable]` Temporary bStock account that should be created prior to this instruction and owned by The Initiator Account
-    /// 3. `[writable]` Temporary bStockLQDY account that should be created prior to this instruction and owned by The Initiator Account
+    /// 0. `[signer, writable]` The Initiator Account, paying the rent of the pool's token accounts
+    /// 1. `[writable]` The PDA's token A account, not created yet
+    /// 2. `[writable]` The PDA's token B account, not created yet
+    /// 3. `[writable]` The LP mint, whose mint authority should be The Initiator Account and will be handed to the PDA, with no freeze authority
     /// 4. `[writable]` The AMM account, it will hold all necessary info about the trade.
     /// 5. `[]` The rent sysvar
     /// 6. `[]` The token program
+    /// 7. `[]` The fee authority, allowed to withdraw the protocol fees
+    /// 8. `[writable]` The token A account owned by The Initiator Account funding the initial liquidity
+    /// 9. `[writable]` The token B account owned by The Initiator Account funding the initial liquidity
+    /// 10. `[writable]` The LP token account receiving The Initiator Account's LP tokens
+    /// 11. `[]` The PDA account
+    /// 12. `[]` The token A mint
+    /// 13. `[]` The token B mint
+    /// 14. `[]` The system program
+    /// 15. `[]` The oracle account if it is a ProactiveMarketMaker pool or guarded by it
     InitAMM {
-
+        trade_fee_numerator: u64,
+        trade_fee_denominator: u64,
+        protocol_fee_numerator: u64,
+        initial_amount_a: u64,
+        initial_amount_b: u64,
+        curve_type: CurveType,
+        amplification: u64,
+        weight_a: u64,
+        end_weight_a: u64,
+        start_timestamp: i64,
+        end_timestamp: i64,
+        tick_spacing: u16,
+        slippage_factor: u64,
+        max_oracle_staleness: u64,
+        max_price_deviation_bps: u64,
     },
 
-    /// Allows liquidity provider to provide {amount (in BUSD and bStock)} of liquidity
-    ///  
+    /// Allows liquidity provider to provide up to {amount_a (in token A) and amount_b (in token B)} of liquidity
+    ///
+    /// The deposit mints the smaller of the two shares of the current reserves and only takes
+    /// the token A and token B needed for that share.
     ///
     /// Accounts expected:
     ///
     /// 0. `[signer]` The liquidity provider's account
-    /// 1. `[writable]` The BUSD token account owned by the liquidity provider's account
-    /// 2. `[writable]` The bStock token account owned by the liquidity provider's account
-    /// 3. `[writable]` The bStockLQDY token account owned by the liquidity provider's account
-    /// 4. `[writable]` The PDA's BUSD token account
-    /// 5. `[writable]` The PDA's bStock token account
-    /// 6. `[writable]` The PDA's bStockLQDY token account
+    /// 1. `[writable]` The token A account owned by the liquidity provider's account
+    /// 2. `[writable]` The token B account owned by the liquidity provider's account
+    /// 3. `[writable]` The LP token account owned by the liquidity provider's account
+    /// 4. `[writable]` The PDA's token A account
+    /// 5. `[writable]` The PDA's token B account
+    /// 6. `[writable]` The LP mint
     /// 7. `[writable]` The AMM account holding the AMM info
     /// 8. `[]` The token program
     /// 9. `[]` The PDA account
-    ProvLiquidity {
-        busd_amount: u64,
-        bstock_amount: u64,
-    },
+    /// 10. `[writable]` Optional: the pool's observation account, see `InitObservations`
+    ProvLiquidity { amount_a: u64, amount_b: u64 },
 
-    /// Allows liquidity provider to claim {amount (in bStockLQDY)} of liquidity
-    ///  
+    /// Allows liquidity provider to claim {amount (in LP tokens)} of liquidity
+    ///
+    /// The LP tokens are burned and its pro-rata share of both PDA token accounts is paid out.
     ///
     /// Accounts expected:
     ///
     /// 0. `[signer]` The liquidity provider's account
-    /// 1. `[writable]` The BUSD token account owned by the liquidity provider's account
-    /// 2. `[writable]` The bStock token account owned by the liquidity provider's account
-    /// 3. `[writable]` The bStockLQDY token account owned by the liquidity provider's account
-    /// 4. `[writable]` The PDA's BUSD token account
-    /// 5. `[writable]` The PDA's bStock token account
-    /// 6. `[writable]` The PDA's bStockLQDY token account
+    /// 1. `[writable]` The token A account owned by the liquidity provider's account
+    /// 2. `[writable]` The token B account owned by the liquidity provider's account
+    /// 3. `[writable]` The LP token account owned by the liquidity provider's account
+    /// 4. `[writable]` The PDA's token A account
+    /// 5. `[writable]` The PDA's token B account
+    /// 6. `[writable]` The LP mint
     /// 7. `[writable]` The AMM account holding the AMM info
     /// 8. `[]` The token program
     /// 9. `[]` The PDA account
-    ClaimLiquidity {
-        amount: u64,
-    },
+    /// 10. `[writable]` Optional: the pool's observation account, see `InitObservations`
+    ClaimLiquidity { amount: u64 },
 
-    /// Allows user to trade {amount (in BUSD)} of BUSD in exchange for bStock
-    ///  
+    /// Allows user to trade {amount_in} of either pool token in exchange for at least {minimum_amount_out} of the other
+    ///
+    /// The direction is picked from the mint of the user's source token account.
     ///
     /// Accounts expected:
     ///
     /// 0. `[signer]` The user's account
-    /// 1. `[writable]` The BUSD token account owned by the user's account
-    /// 2. `[writable]` The bStock token account owned by the user's account
-    /// 3. `[writable]` The PDA's BUSD token account
-    /// 4. `[writable]` The PDA's bStock token account
+    /// 1. `[writable]` The user's token account of the token being sold
+    /// 2. `[writable]` The user's token account of the token being bought
+    /// 3. `[writable]` The PDA's token A account
+    /// 4. `[writable]` The PDA's token B account
     /// 5. `[writable]` The AMM account holding the AMM info
     /// 6. `[]` The token program
     /// 7. `[]` The PDA account
-    TradeBUSD {
-        amount: u64,
+    /// 8. `[writable]` Optional: the pool's observation account, see `InitObservations`
+    /// 9. `[writable]` The pool's ticks account if it is a Concentrated pool, see `InitTicks`
+    /// 10. `[]` The pool's oracle account if it is a ProactiveMarketMaker pool or guarded by it
+    Swap {
+        amount_in: u64,
+        minimum_amount_out: u64,
     },
 
-    /// Allows user to trade {amount (in bStock)} of bStock in exchange for BUSD
-    ///  
+    /// Allows user to buy exactly {amount_out} of either pool token for at most {maximum_amount_in} of the other
+    ///
+    /// The direction is picked from the mint of the user's source token account.
     ///
     /// Accounts expected:
     ///
     /// 0. `[signer]` The user's account
-    /// 1. `[writable]` The BUSD token account owned by the user's account
-    /// 2. `[writable]` The bStock token account owned by the user's account
-    /// 3. `[writable]` The PDA's BUSD token account
-    /// 4. `[writable]` The PDA's bStock token account
+    /// 1. `[writable]` The user's token account of the token being sold
+    /// 2. `[writable]` The user's token account of the token being bought
+    /// 3. `[writable]` The PDA's token A account
+    /// 4. `[writable]` The PDA's token B account
     /// 5. `[writable]` The AMM account holding the AMM info
     /// 6. `[]` The token program
     /// 7. `[]` The PDA account
-    TradebStock {
-        amount: u64,
+    /// 8. `[writable]` Optional: the pool's observation account, see `InitObservations`
+    /// 9. `[writable]` The pool's ticks account if it is a Concentrated pool, see `InitTicks`
+    /// 10. `[]` The pool's oracle account if it is a ProactiveMarketMaker pool or guarded by it
+    SwapExactOut {
+        amount_out: u64,
+        maximum_amount_in: u64,
     },
 
     /// Closes the AMM
-    ///  
+    ///
+    /// All LP tokens must have been redeemed and all protocol fees withdrawn. The liquidity
+    /// locked by the first deposit is sent back to The Initiator Account, along with any tokens that
+    /// were sent to the PDA's token accounts directly and never skimmed.
     ///
     /// Accounts expected:
     ///
-    /// 0. `[signer]` The Initiator Account
-    /// 1. `[writable]` The PDA's BUSD token account
-    /// 2. `[writable]` The PDA's bStock token account
-    /// 3. `[writable]` The PDA's bStockLQDY token account
-    /// 4. `[writable]` The AMM account holding the AMM info
+    /// 0. `[signer, writable]` The Initiator Account, receiving the rent of the closed accounts
+    /// 1. `[writable]` The token A account receiving the locked token A
+    /// 2. `[writable]` The token B account receiving the locked token B
+    /// 3. `[writable]` The PDA's token A account
+    /// 4. `[writable]` The PDA's token B account
+    /// 5. `[]` The LP mint
+    /// 6. `[writable]` The AMM account holding the AMM info
+    /// 7. `[]` The token program
+    /// 8. `[]` The PDA account
+    CloseAMM {},
+
+    /// Allows the fee authority to withdraw all protocol fees accrued so far
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The fee authority
+    /// 1. `[writable]` The token A account receiving the token A fees
+    /// 2. `[writable]` The token B account receiving the token B fees
+    /// 3. `[writable]` The PDA's token A account
+    /// 4. `[writable]` The PDA's token B account
+    /// 5. `[writable]` The AMM account holding the AMM info
+    /// 6. `[]` The token program
+    /// 7. `[]` The PDA account
+    WithdrawProtocolFees {},
+
+    /// Sets the pool reserves recorded in the AMM to the PDA's token A and token B balances, net of protocol fees
+    ///
+    /// Trades are priced from the recorded reserves only, so tokens sent to the PDA's token accounts
+    /// directly are ignored until someone calls this instruction.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[]` The PDA's token A account
+    /// 1. `[]` The PDA's token B account
+    /// 2. `[writable]` The AMM account holding the AMM info
+    /// 3. `[writable]` Optional: the pool's observation account, see `InitObservations`
+    Sync {},
+
+    /// Sends whatever the PDA's token accounts hold above the recorded pool reserves and protocol fees to the given token accounts
+    ///
+    /// Concentrated pools hold the fees owed to their positions above the reserves and cannot be skimmed.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[writable]` The token A account receiving the excess token A
+    /// 1. `[writable]` The token B account receiving the excess token B
+    /// 2. `[writable]` The PDA's token A account
+    /// 3. `[writable]` The PDA's token B account
+    /// 4. `[]` The AMM account holding the AMM info
     /// 5. `[]` The token program
     /// 6. `[]` The PDA account
-    CloseAMM {
-        
+    Skim {},
+
+    /// Creates the pool's observation account, a ring buffer of the AMM's cumulative prices over time
+    ///
+    /// The account lives at the address derived from the seeds `["bravv", AMM account, "observations"]`.
+    /// Once it exists, every instruction updating the cumulative prices that is passed the observation
+    /// account records them in it, at most once per slot. The instruction builders of this module
+    /// always pass it.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The account paying the rent of the observation account
+    /// 1. `[writable]` The pool's observation account, not created yet
+    /// 2. `[]` The AMM account holding the AMM info
+    /// 3. `[]` The system program
+    InitObservations {},
+
+    /// Returns the average prices of the pool over the last {window_seconds} through the program return data
+    ///
+    /// The cumulative prices at the start of the window are interpolated between the observations around it,
+    /// so the pool's observation account must reach back that far. The return data holds the average price of token A
+    /// in token B followed by the average price of token B in token A, each a little-endian UQ64.64 `u128`.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[]` The AMM account holding the AMM info
+    /// 1. `[]` The pool's observation account
+    QueryTwap { window_seconds: u64 },
+
+    /// Returns what a `Swap` of {amount_in} would pay out right now through the program return data,
+    /// selling token A if {a_to_b} and token B otherwise
+    ///
+    /// The trade is priced by the same code as `Swap`. The return data holds three little-endian `u64`s:
+    /// the amount out, the fee taken from `amount_in` and the price impact in basis points, which is how
+    /// much worse than the spot price the part of `amount_in` left after the fee trades at. Like `Swap`, it fails
+    /// if the trade would move a guarded pool's price too far from its oracle price.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[]` The AMM account holding the AMM info
+    /// 1. `[]` The pool's ticks account, only passed for a Concentrated pool, see `InitTicks`
+    /// 2. `[]` The pool's oracle account, only passed for a ProactiveMarketMaker pool or a pool guarded by it
+    ///
+    /// The optional accounts are read in this order and only for pools that have them, so the oracle account
+    /// directly follows the AMM account of a pool without a ticks account.
+    Quote { amount_in: u64, a_to_b: bool },
+
+    /// Allows The Initiator Account to move a StableSwap pool's amplification coefficient to {target_amplification}
+    ///
+    /// The coefficient moves linearly from its current value to `target_amplification`, reached at the unix
+    /// timestamp {end_timestamp}, and every trade prices against its value at the time of the trade. Changing it
+    /// at once would hand arbitrageurs the difference, hence a ramp lasts at least a day and may at most
+    /// multiply or divide the coefficient by 10. Starting a new ramp replaces the current one.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The Initiator Account
+    /// 1. `[writable]` The AMM account holding the AMM info
+    RampAmplification {
+        target_amplification: u64,
+        end_timestamp: i64,
+    },
+
+    /// Allows The Initiator Account to stop the current ramp of a StableSwap pool, freezing the amplification coefficient at its current value
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The Initiator Account
+    /// 1. `[writable]` The AMM account holding the AMM info
+    StopRamp {},
+
+    /// Creates the ticks account of a Concentrated pool, holding the ticks its positions start or end at
+    ///
+    /// The account lives at the address derived from the seeds `["bravv", AMM account, "ticks"]` and holds up to
+    /// 128 ticks.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The account paying the rent of the ticks account
+    /// 1. `[writable]` The pool's ticks account, not created yet
+    /// 2. `[]` The AMM account holding the AMM info
+    /// 3. `[]` The system program
+    InitTicks {},
+
+    /// Allows the position owner to add {liquidity} to their position from {tick_lower} to {tick_upper} in a
+    /// Concentrated pool, for at most {maximum_amount_a} of token A and {maximum_amount_b} of token B
+    ///
+    /// The position lives at the address derived from the seeds
+    /// `["bravv", AMM account, "position", owner, tick_lower, tick_upper]`, the ticks as little-endian `i32`s,
+    /// and is created by the program the first time. The position only trades while the price is within its
+    /// range: it takes only token A while the price is below the range, only token B above it, and both in
+    /// proportion to the current price within it. Its share of the trading fee accrues while it trades,
+    /// see `CollectFees`.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The position owner, paying the rent of the position account
+    /// 1. `[writable]` The token A account owned by the position owner
+    /// 2. `[writable]` The token B account owned by the position owner
+    /// 3. `[writable]` The PDA's token A account
+    /// 4. `[writable]` The PDA's token B account
+    /// 5. `[writable]` The AMM account holding the AMM info
+    /// 6. `[writable]` The pool's ticks account
+    /// 7. `[writable]` The position account
+    /// 8. `[]` The token program
+    /// 9. `[]` The system program
+    IncreaseLiquidity {
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u64,
+        maximum_amount_a: u64,
+        maximum_amount_b: u64,
+    },
+
+    /// Allows the position owner to remove {liquidity} from their position, for at least {minimum_amount_a}
+    /// of token A and {minimum_amount_b} of token B
+    ///
+    /// The fees earned so far stay in the position until collected with `CollectFees`.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The position owner
+    /// 1. `[writable]` The token A account owned by the position owner
+    /// 2. `[writable]` The token B account owned by the position owner
+    /// 3. `[writable]` The PDA's token A account
+    /// 4. `[writable]` The PDA's token B account
+    /// 5. `[writable]` The AMM account holding the AMM info
+    /// 6. `[writable]` The pool's ticks account
+    /// 7. `[writable]` The position account
+    /// 8. `[]` The token program
+    /// 9. `[]` The PDA account
+    DecreaseLiquidity {
+        liquidity: u64,
+        minimum_amount_a: u64,
+        minimum_amount_b: u64,
     },
+
+    /// Allows the position owner to collect all the fees their position has earned so far
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The position owner
+    /// 1. `[writable]` The token A account receiving the token A fees
+    /// 2. `[writable]` The token B account receiving the token B fees
+    /// 3. `[writable]` The PDA's token A account
+    /// 4. `[writable]` The PDA's token B account
+    /// 5. `[writable]` The AMM account holding the AMM info
+    /// 6. `[writable]` The pool's ticks account
+    /// 7. `[writable]` The position account
+    /// 8. `[]` The token program
+    /// 9. `[]` The PDA account
+    CollectFees {},
+
+    /// Initializes an oracle account publishing {price}, the price of token A in token B as a UQ64.64
+    /// fixed-point number, for ProactiveMarketMaker pools to trade against
+    ///
+    /// The oracle account must be owned by this program. Only the oracle authority may update the price
+    /// afterwards, see `UpdateOracle`, and every update is timestamped so pools can tell stale prices apart.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The oracle authority
+    /// 1. `[writable]` The oracle account, not initialized yet
+    /// 2. `[]` The rent sysvar
+    InitOracle { price: u128 },
+
+    /// Allows the oracle authority to publish {price}, the price of token A in token B as a UQ64.64
+    /// fixed-point number
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The oracle authority
+    /// 1. `[writable]` The oracle account
+    UpdateOracle { price: u128 },
 }
 
 impl AMMInstruction {
-    /// Unpacks a byte buffer into a [EscrowInstruction](enum.EscrowInstruction.html).
+    /// Unpacks a byte buffer into a [AMMInstruction](enum.AMMInstruction.html).
     pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
         let (tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
 
         Ok(match tag {
-            0 => Self::InitAMM {},
+            0 => Self::InitAMM {
+                trade_fee_numerator: Self::unpack_amount(rest)?,
+                trade_fee_denominator: Self::unpack_second_amount(rest)?,
+                protocol_fee_numerator: Self::unpack_third_amount(rest)?,
+                initial_amount_a: Self::unpack_fourth_amount(rest)?,
+                initial_amount_b: Self::unpack_fifth_amount(rest)?,
+                curve_type: Self::unpack_curve_type(rest)?,
+                amplification: Self::unpack_amplification(rest)?,
+                weight_a: Self::unpack_weight(rest)?,
+                end_weight_a: Self::unpack_end_weight(rest)?,
+                start_timestamp: Self::unpack_start_timestamp(rest)?,
+                end_timestamp: Self::unpack_end_timestamp(rest)?,
+                tick_spacing: Self::unpack_tick_spacing(rest)?,
+                slippage_factor: Self::unpack_slippage_factor(rest)?,
+                max_oracle_staleness: Self::unpack_max_oracle_staleness(rest)?,
+                max_price_deviation_bps: Self::unpack_max_price_deviation_bps(rest)?,
+            },
             1 => Self::ProvLiquidity {
-                busd_amount: Self::unpack_amount(rest)?,
-                bstock_amount: Self::unpack_second_amount(rest)?,
+                amount_a: Self::unpack_amount(rest)?,
+                amount_b: Self::unpack_second_amount(rest)?,
             },
             2 => Self::ClaimLiquidity {
                 amount: Self::unpack_amount(rest)?,
             },
-            3 => Self::TradeBUSD {
-                amount: Self::unpack_amount(rest)?,
+            3 => Self::Swap {
+                amount_in: Self::unpack_amount(rest)?,
+                minimum_amount_out: Self::unpack_second_amount(rest)?,
             },
-            4 => Self::TradebStock {
-                amount: Self::unpack_amount(rest)?,
+            4 => Self::SwapExactOut {
+                amount_out: Self::unpack_amount(rest)?,
+                maximum_amount_in: Self::unpack_second_amount(rest)?,
             },
             5 => Self::CloseAMM {},
+            6 => Self::WithdrawProtocolFees {},
+            7 => Self::Sync {},
+            8 => Self::Skim {},
+            9 => Self::InitObservations {},
+            10 => Self::QueryTwap {
+                window_seconds: Self::unpack_amount(rest)?,
+            },
+            11 => Self::Quote {
+                amount_in: Self::unpack_amount(rest)?,
+                a_to_b: Self::unpack_direction(rest)?,
+            },
+            12 => Self::RampAmplification {
+                target_amplification: Self::unpack_amount(rest)?,
+                end_timestamp: Self::unpack_second_amount(rest)? as i64,
+            },
+            13 => Self::StopRamp {},
+            14 => Self::InitTicks {},
+            15 => Self::IncreaseLiquidity {
+                tick_lower: Self::unpack_tick_lower(rest)?,
+                tick_upper: Self::unpack_tick_upper(rest)?,
+                liquidity: Self::unpack_second_amount(rest)?,
+                maximum_amount_a: Self::unpack_third_amount(rest)?,
+                maximum_amount_b: Self::unpack_fourth_amount(rest)?,
+            },
+            16 => Self::DecreaseLiquidity {
+                liquidity: Self::unpack_amount(rest)?,
+                minimum_amount_a: Self::unpack_second_amount(rest)?,
+                minimum_amount_b: Self::unpack_third_amount(rest)?,
+            },
+            17 => Self::CollectFees {},
+            18 => Self::InitOracle {
+                price: Self::unpack_price(rest)?,
+            },
+            19 => Self::UpdateOracle {
+                price: Self::unpack_price(rest)?,
+            },
             _ => return Err(InvalidInstruction.into()),
         })
     }
 
+    /// Packs a [AMMInstruction](enum.AMMInstruction.html) into a byte buffer.
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(2 + 13 * size_of::<u64>() + size_of::<u16>());
+        match self {
+            Self::InitAMM {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                protocol_fee_numerator,
+                initial_amount_a,
+                initial_amount_b,
+                curve_type,
+                amplification,
+                weight_a,
+                end_weight_a,
+                start_timestamp,
+                end_timestamp,
+                tick_spacing,
+                slippage_factor,
+                max_oracle_staleness,
+                max_price_deviation_bps,
+            } => {
+                buf.push(0);
+                buf.extend_from_slice(&trade_fee_numerator.to_le_bytes());
+                buf.extend_from_slice(&trade_fee_denominator.to_le_bytes());
+                buf.extend_from_slice(&protocol_fee_numerator.to_le_bytes());
+                buf.extend_from_slice(&initial_amount_a.to_le_bytes());
+                buf.extend_from_slice(&initial_amount_b.to_le_bytes());
+                buf.push(*curve_type as u8);
+                buf.extend_from_slice(&amplification.to_le_bytes());
+                buf.extend_from_slice(&weight_a.to_le_bytes());
+                buf.extend_from_slice(&end_weight_a.to_le_bytes());
+                buf.extend_from_slice(&start_timestamp.to_le_bytes());
+                buf.extend_from_slice(&end_timestamp.to_le_bytes());
+                buf.extend_from_slice(&tick_spacing.to_le_bytes());
+                buf.extend_from_slice(&slippage_factor.to_le_bytes());
+                buf.extend_from_slice(&max_oracle_staleness.to_le_bytes());
+                buf.extend_from_slice(&max_price_deviation_bps.to_le_bytes());
+            }
+            Self::ProvLiquidity { amount_a, amount_b } => {
+                buf.push(1);
+                buf.extend_from_slice(&amount_a.to_le_bytes());
+                buf.extend_from_slice(&amount_b.to_le_bytes());
+            }
+            Self::ClaimLiquidity { amount } => {
+                buf.push(2);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::Swap {
+                amount_in,
+                minimum_amount_out,
+            } => {
+                buf.push(3);
+                buf.extend_from_slice(&amount_in.to_le_bytes());
+                buf.extend_from_slice(&minimum_amount_out.to_le_bytes());
+            }
+            Self::SwapExactOut {
+                amount_out,
+                maximum_amount_in,
+            } => {
+                buf.push(4);
+                buf.extend_from_slice(&amount_out.to_le_bytes());
+                buf.extend_from_slice(&maximum_amount_in.to_le_bytes());
+            }
+            Self::CloseAMM {} => buf.push(5),
+            Self::WithdrawProtocolFees {} => buf.push(6),
+            Self::Sync {} => buf.push(7),
+            Self::Skim {} => buf.push(8),
+            Self::InitObservations {} => buf.push(9),
+            Self::QueryTwap { window_seconds } => {
+                buf.push(10);
+                buf.extend_from_slice(&window_seconds.to_le_bytes());
+            }
+            Self::Quote { amount_in, a_to_b } => {
+                buf.push(11);
+                buf.extend_from_slice(&amount_in.to_le_bytes());
+                buf.push(*a_to_b as u8);
+            }
+            Self::RampAmplification {
+                target_amplification,
+                end_timestamp,
+            } => {
+                buf.push(12);
+                buf.extend_from_slice(&target_amplification.to_le_bytes());
+                buf.extend_from_slice(&end_timestamp.to_le_bytes());
+            }
+            Self::StopRamp {} => buf.push(13),
+            Self::InitTicks {} => buf.push(14),
+            Self::IncreaseLiquidity {
+                tick_lower,
+                tick_upper,
+                liquidity,
+                maximum_amount_a,
+                maximum_amount_b,
+            } => {
+                buf.push(15);
+                buf.extend_from_slice(&tick_lower.to_le_bytes());
+                buf.extend_from_slice(&tick_upper.to_le_bytes());
+                buf.extend_from_slice(&liquidity.to_le_bytes());
+                buf.extend_from_slice(&maximum_amount_a.to_le_bytes());
+                buf.extend_from_slice(&maximum_amount_b.to_le_bytes());
+            }
+            Self::DecreaseLiquidity {
+                liquidity,
+                minimum_amount_a,
+                minimum_amount_b,
+            } => {
+                buf.push(16);
+                buf.extend_from_slice(&liquidity.to_le_bytes());
+                buf.extend_from_slice(&minimum_amount_a.to_le_bytes());
+                buf.extend_from_slice(&minimum_amount_b.to_le_bytes());
+            }
+            Self::CollectFees {} => buf.push(17),
+            Self::InitOracle { price } => {
+                buf.push(18);
+                buf.extend_from_slice(&price.to_le_bytes());
+            }
+            Self::UpdateOracle { price } => {
+                buf.push(19);
+                buf.extend_from_slice(&price.to_le_bytes());
+            }
+        }
+        buf
+    }
+
     fn unpack_amount(input: &[u8]) -> Result<u64, ProgramError> {
         let amount = input
             .get(0..8)
@@ -151,4 +636,900 @@ impl AMMInstruction {
             .ok_or(InvalidInstruction)?;
         Ok(amount)
     }
+    fn unpack_third_amount(input: &[u8]) -> Result<u64, ProgramError> {
+        let amount = input
+            .get(16..24)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok(amount)
+    }
+    fn unpack_fourth_amount(input: &[u8]) -> Result<u64, ProgramError> {
+        let amount = input
+            .get(24..32)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok(amount)
+    }
+    fn unpack_fifth_amount(input: &[u8]) -> Result<u64, ProgramError> {
+        let amount = input
+            .get(32..40)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok(amount)
+    }
+    fn unpack_curve_type(input: &[u8]) -> Result<CurveType, ProgramError> {
+        let curve_type = input.get(40).ok_or(InvalidInstruction)?;
+        CurveType::try_from(*curve_type).map_err(|_| InvalidInstruction.into())
+    }
+    fn unpack_amplification(input: &[u8]) -> Result<u64, ProgramError> {
+        let amount = input
+            .get(41..49)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok(amount)
+    }
+    fn unpack_weight(input: &[u8]) -> Result<u64, ProgramError> {
+        let weight = input
+            .get(49..57)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok(weight)
+    }
+    fn unpack_end_weight(input: &[u8]) -> Result<u64, ProgramError> {
+        let weight = input
+            .get(57..65)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok(weight)
+    }
+    fn unpack_start_timestamp(input: &[u8]) -> Result<i64, ProgramError> {
+        let timestamp = input
+            .get(65..73)
+            .and_then(|slice| slice.try_into().ok())
+            .map(i64::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok(timestamp)
+    }
+    fn unpack_end_timestamp(input: &[u8]) -> Result<i64, ProgramError> {
+        let timestamp = input
+            .get(73..81)
+            .and_then(|slice| slice.try_into().ok())
+            .map(i64::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok(timestamp)
+    }
+    fn unpack_tick_spacing(input: &[u8]) -> Result<u16, ProgramError> {
+        let tick_spacing = input
+            .get(81..83)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u16::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok(tick_spacing)
+    }
+    fn unpack_slippage_factor(input: &[u8]) -> Result<u64, ProgramError> {
+        let slippage_factor = input
+            .get(83..91)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok(slippage_factor)
+    }
+    fn unpack_max_oracle_staleness(input: &[u8]) -> Result<u64, ProgramError> {
+        let seconds = input
+            .get(91..99)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok(seconds)
+    }
+    fn unpack_max_price_deviation_bps(input: &[u8]) -> Result<u64, ProgramError> {
+        let bps = input
+            .get(99..107)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok(bps)
+    }
+    fn unpack_price(input: &[u8]) -> Result<u128, ProgramError> {
+        let price = input
+            .get(0..16)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u128::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok(price)
+    }
+    fn unpack_tick_lower(input: &[u8]) -> Result<i32, ProgramError> {
+        let tick = input
+            .get(0..4)
+            .and_then(|slice| slice.try_into().ok())
+            .map(i32::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok(tick)
+    }
+    fn unpack_tick_upper(input: &[u8]) -> Result<i32, ProgramError> {
+        let tick = input
+            .get(4..8)
+            .and_then(|slice| slice.try_into().ok())
+            .map(i32::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok(tick)
+    }
+    fn unpack_direction(input: &[u8]) -> Result<bool, ProgramError> {
+        match input.get(8) {
+            Some(0) => Ok(false),
+            Some(1) => Ok(true),
+            _ => Err(InvalidInstruction.into()),
+        }
+    }
+}
+
+/// Derives the PDA owning the token accounts of the pool stored in `amm_account`
+pub fn find_pool_authority(program_id: &Pubkey, amm_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"bravv", amm_account.as_ref()], program_id)
+}
+
+/// Derives the address of the token account holding `mint` for the pool stored in `amm_account`
+pub fn find_pool_vault(program_id: &Pubkey, amm_account: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"bravv", amm_account.as_ref(), mint.as_ref()], program_id)
+}
+
+/// Derives the address of the observation account of the pool stored in `amm_account`
+pub fn find_observations_address(program_id: &Pubkey, amm_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"bravv", amm_account.as_ref(), b"observations"],
+        program_id,
+    )
+}
+
+/// Derives the address of the ticks account of the concentrated-liquidity pool stored in `amm_account`
+pub fn find_ticks_address(program_id: &Pubkey, amm_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"bravv", amm_account.as_ref(), b"ticks"], program_id)
+}
+
+/// Derives the address of the position of `owner` from `tick_lower` to `tick_upper` in the pool stored in `amm_account`
+pub fn find_position_address(
+    program_id: &Pubkey,
+    amm_account: &Pubkey,
+    owner: &Pubkey,
+    tick_lower: i32,
+    tick_upper: i32,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"bravv",
+            amm_account.as_ref(),
+            b"position",
+            owner.as_ref(),
+            &tick_lower.to_le_bytes(),
+            &tick_upper.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Creates an `InitAMM` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn init_amm(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+    lp_mint: &Pubkey,
+    amm_account: &Pubkey,
+    fee_authority: &Pubkey,
+    initializer_token_a_account: &Pubkey,
+    initializer_token_b_account: &Pubkey,
+    initializer_lp_token_account: &Pubkey,
+    trade_fee_numerator: u64,
+    trade_fee_denominator: u64,
+    protocol_fee_numerator: u64,
+    initial_amount_a: u64,
+    initial_amount_b: u64,
+    curve_type: CurveType,
+    amplification: u64,
+    weight_a: u64,
+    end_weight_a: u64,
+    start_timestamp: i64,
+    end_timestamp: i64,
+    tick_spacing: u16,
+    slippage_factor: u64,
+    max_oracle_staleness: u64,
+    max_price_deviation_bps: u64,
+    oracle: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::InitAMM {
+        trade_fee_numerator,
+        trade_fee_denominator,
+        protocol_fee_numerator,
+        initial_amount_a,
+        initial_amount_b,
+        curve_type,
+        amplification,
+        weight_a,
+        end_weight_a,
+        start_timestamp,
+        end_timestamp,
+        tick_spacing,
+        slippage_factor,
+        max_oracle_staleness,
+        max_price_deviation_bps,
+    }
+    .pack();
+    let (pda, _bump_seed) = find_pool_authority(program_id, amm_account);
+    let (pda_token_a_account, _bump_seed) = find_pool_vault(program_id, amm_account, mint_a);
+    let (pda_token_b_account, _bump_seed) = find_pool_vault(program_id, amm_account, mint_b);
+
+    let mut accounts = vec![
+        AccountMeta::new(*initializer, true),
+        AccountMeta::new(pda_token_a_account, false),
+        AccountMeta::new(pda_token_b_account, false),
+        AccountMeta::new(*lp_mint, false),
+        AccountMeta::new(*amm_account, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(*fee_authority, false),
+        AccountMeta::new(*initializer_token_a_account, false),
+        AccountMeta::new(*initializer_token_b_account, false),
+        AccountMeta::new(*initializer_lp_token_account, false),
+        AccountMeta::new_readonly(pda, false),
+        AccountMeta::new_readonly(*mint_a, false),
+        AccountMeta::new_readonly(*mint_b, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    if let Some(oracle) = oracle {
+        accounts.push(AccountMeta::new_readonly(*oracle, false));
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `ProvLiquidity` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn provide_liquidity(
+    program_id: &Pubkey,
+    liquidity_provider: &Pubkey,
+    liquidity_provider_token_a_account: &Pubkey,
+    liquidity_provider_token_b_account: &Pubkey,
+    liquidity_provider_lp_token_account: &Pubkey,
+    pda_token_a_account: &Pubkey,
+    pda_token_b_account: &Pubkey,
+    lp_mint: &Pubkey,
+    amm_account: &Pubkey,
+    amount_a: u64,
+    amount_b: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::ProvLiquidity { amount_a, amount_b }.pack();
+    let (pda, _bump_seed) = find_pool_authority(program_id, amm_account);
+    let (observations, _bump_seed) = find_observations_address(program_id, amm_account);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*liquidity_provider, true),
+        AccountMeta::new(*liquidity_provider_token_a_account, false),
+        AccountMeta::new(*liquidity_provider_token_b_account, false),
+        AccountMeta::new(*liquidity_provider_lp_token_account, false),
+        AccountMeta::new(*pda_token_a_account, false),
+        AccountMeta::new(*pda_token_b_account, false),
+        AccountMeta::new(*lp_mint, false),
+        AccountMeta::new(*amm_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(pda, false),
+        AccountMeta::new(observations, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `ClaimLiquidity` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn claim_liquidity(
+    program_id: &Pubkey,
+    liquidity_provider: &Pubkey,
+    liquidity_provider_token_a_account: &Pubkey,
+    liquidity_provider_token_b_account: &Pubkey,
+    liquidity_provider_lp_token_account: &Pubkey,
+    pda_token_a_account: &Pubkey,
+    pda_token_b_account: &Pubkey,
+    lp_mint: &Pubkey,
+    amm_account: &Pubkey,
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::ClaimLiquidity { amount }.pack();
+    let (pda, _bump_seed) = find_pool_authority(program_id, amm_account);
+    let (observations, _bump_seed) = find_observations_address(program_id, amm_account);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*liquidity_provider, true),
+        AccountMeta::new(*liquidity_provider_token_a_account, false),
+        AccountMeta::new(*liquidity_provider_token_b_account, false),
+        AccountMeta::new(*liquidity_provider_lp_token_account, false),
+        AccountMeta::new(*pda_token_a_account, false),
+        AccountMeta::new(*pda_token_b_account, false),
+        AccountMeta::new(*lp_mint, false),
+        AccountMeta::new(*amm_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(pda, false),
+        AccountMeta::new(observations, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `Swap` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn swap(
+    program_id: &Pubkey,
+    user: &Pubkey,
+    user_source_token_account: &Pubkey,
+    user_destination_token_account: &Pubkey,
+    pda_token_a_account: &Pubkey,
+    pda_token_b_account: &Pubkey,
+    amm_account: &Pubkey,
+    amount_in: u64,
+    minimum_amount_out: u64,
+    oracle: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::Swap {
+        amount_in,
+        minimum_amount_out,
+    }
+    .pack();
+    let accounts = trade_accounts(
+        program_id,
+        user,
+        user_source_token_account,
+        user_destination_token_account,
+        pda_token_a_account,
+        pda_token_b_account,
+        amm_account,
+        oracle,
+    );
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `SwapExactOut` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn swap_exact_out(
+    program_id: &Pubkey,
+    user: &Pubkey,
+    user_source_token_account: &Pubkey,
+    user_destination_token_account: &Pubkey,
+    pda_token_a_account: &Pubkey,
+    pda_token_b_account: &Pubkey,
+    amm_account: &Pubkey,
+    amount_out: u64,
+    maximum_amount_in: u64,
+    oracle: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::SwapExactOut {
+        amount_out,
+        maximum_amount_in,
+    }
+    .pack();
+    let accounts = trade_accounts(
+        program_id,
+        user,
+        user_source_token_account,
+        user_destination_token_account,
+        pda_token_a_account,
+        pda_token_b_account,
+        amm_account,
+        oracle,
+    );
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn trade_accounts(
+    program_id: &Pubkey,
+    user: &Pubkey,
+    user_source_token_account: &Pubkey,
+    user_destination_token_account: &Pubkey,
+    pda_token_a_account: &Pubkey,
+    pda_token_b_account: &Pubkey,
+    amm_account: &Pubkey,
+    oracle: Option<&Pubkey>,
+) -> Vec<AccountMeta> {
+    let (pda, _bump_seed) = find_pool_authority(program_id, amm_account);
+    let (observations, _bump_seed) = find_observations_address(program_id, amm_account);
+    let (ticks, _bump_seed) = find_ticks_address(program_id, amm_account);
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*user, true),
+        AccountMeta::new(*user_source_token_account, false),
+        AccountMeta::new(*user_destination_token_account, false),
+        AccountMeta::new(*pda_token_a_account, false),
+        AccountMeta::new(*pda_token_b_account, false),
+        AccountMeta::new(*amm_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(pda, false),
+        AccountMeta::new(observations, false),
+        AccountMeta::new(ticks, false),
+    ];
+    if let Some(oracle) = oracle {
+        accounts.push(AccountMeta::new_readonly(*oracle, false));
+    }
+    accounts
+}
+
+/// Creates a `CloseAMM` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn close_amm(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    initializer_token_a_account: &Pubkey,
+    initializer_token_b_account: &Pubkey,
+    pda_token_a_account: &Pubkey,
+    pda_token_b_account: &Pubkey,
+    lp_mint: &Pubkey,
+    amm_account: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::CloseAMM {}.pack();
+    let (pda, _bump_seed) = find_pool_authority(program_id, amm_account);
+
+    let accounts = vec![
+        AccountMeta::new(*initializer, true),
+        AccountMeta::new(*initializer_token_a_account, false),
+        AccountMeta::new(*initializer_token_b_account, false),
+        AccountMeta::new(*pda_token_a_account, false),
+        AccountMeta::new(*pda_token_b_account, false),
+        AccountMeta::new_readonly(*lp_mint, false),
+        AccountMeta::new(*amm_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(pda, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `WithdrawProtocolFees` instruction
+pub fn withdraw_protocol_fees(
+    program_id: &Pubkey,
+    fee_authority: &Pubkey,
+    destination_token_a_account: &Pubkey,
+    destination_token_b_account: &Pubkey,
+    pda_token_a_account: &Pubkey,
+    pda_token_b_account: &Pubkey,
+    amm_account: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::WithdrawProtocolFees {}.pack();
+    let (pda, _bump_seed) = find_pool_authority(program_id, amm_account);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*fee_authority, true),
+        AccountMeta::new(*destination_token_a_account, false),
+        AccountMeta::new(*destination_token_b_account, false),
+        AccountMeta::new(*pda_token_a_account, false),
+        AccountMeta::new(*pda_token_b_account, false),
+        AccountMeta::new(*amm_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(pda, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `Sync` instruction
+pub fn sync(
+    program_id: &Pubkey,
+    pda_token_a_account: &Pubkey,
+    pda_token_b_account: &Pubkey,
+    amm_account: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::Sync {}.pack();
+    let (observations, _bump_seed) = find_observations_address(program_id, amm_account);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*pda_token_a_account, false),
+        AccountMeta::new_readonly(*pda_token_b_account, false),
+        AccountMeta::new(*amm_account, false),
+        AccountMeta::new(observations, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `Skim` instruction
+pub fn skim(
+    program_id: &Pubkey,
+    destination_token_a_account: &Pubkey,
+    destination_token_b_account: &Pubkey,
+    pda_token_a_account: &Pubkey,
+    pda_token_b_account: &Pubkey,
+    amm_account: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::Skim {}.pack();
+    let (pda, _bump_seed) = find_pool_authority(program_id, amm_account);
+
+    let accounts = vec![
+        AccountMeta::new(*destination_token_a_account, false),
+        AccountMeta::new(*destination_token_b_account, false),
+        AccountMeta::new(*pda_token_a_account, false),
+        AccountMeta::new(*pda_token_b_account, false),
+        AccountMeta::new_readonly(*amm_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(pda, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an `InitObservations` instruction
+pub fn init_observations(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    amm_account: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::InitObservations {}.pack();
+    let (observations, _bump_seed) = find_observations_address(program_id, amm_account);
+
+    let accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(observations, false),
+        AccountMeta::new_readonly(*amm_account, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `QueryTwap` instruction
+pub fn query_twap(
+    program_id: &Pubkey,
+    amm_account: &Pubkey,
+    window_seconds: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::QueryTwap { window_seconds }.pack();
+    let (observations, _bump_seed) = find_observations_address(program_id, amm_account);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*amm_account, false),
+        AccountMeta::new_readonly(observations, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `Quote` instruction for a pool on `curve_type`, passing its ticks account if it is a Concentrated pool
+/// and then `oracle`, which must be the pool's oracle account if it has one
+pub fn quote(
+    program_id: &Pubkey,
+    amm_account: &Pubkey,
+    curve_type: CurveType,
+    amount_in: u64,
+    a_to_b: bool,
+    oracle: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::Quote { amount_in, a_to_b }.pack();
+
+    let mut accounts = vec![AccountMeta::new_readonly(*amm_account, false)];
+    if curve_type == CurveType::Concentrated {
+        let (ticks, _bump_seed) = find_ticks_address(program_id, amm_account);
+        accounts.push(AccountMeta::new_readonly(ticks, false));
+    }
+    if let Some(oracle) = oracle {
+        accounts.push(AccountMeta::new_readonly(*oracle, false));
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `RampAmplification` instruction
+pub fn ramp_amplification(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    amm_account: &Pubkey,
+    target_amplification: u64,
+    end_timestamp: i64,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::RampAmplification {
+        target_amplification,
+        end_timestamp,
+    }
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*initializer, true),
+        AccountMeta::new(*amm_account, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `StopRamp` instruction
+pub fn stop_ramp(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    amm_account: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::StopRamp {}.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*initializer, true),
+        AccountMeta::new(*amm_account, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an `InitTicks` instruction
+pub fn init_ticks(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    amm_account: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::InitTicks {}.pack();
+    let (ticks, _bump_seed) = find_ticks_address(program_id, amm_account);
+
+    let accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(ticks, false),
+        AccountMeta::new_readonly(*amm_account, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an `IncreaseLiquidity` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn increase_liquidity(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    owner_token_a_account: &Pubkey,
+    owner_token_b_account: &Pubkey,
+    pda_token_a_account: &Pubkey,
+    pda_token_b_account: &Pubkey,
+    amm_account: &Pubkey,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u64,
+    maximum_amount_a: u64,
+    maximum_amount_b: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::IncreaseLiquidity {
+        tick_lower,
+        tick_upper,
+        liquidity,
+        maximum_amount_a,
+        maximum_amount_b,
+    }
+    .pack();
+    let (ticks, _bump_seed) = find_ticks_address(program_id, amm_account);
+    let (position, _bump_seed) =
+        find_position_address(program_id, amm_account, owner, tick_lower, tick_upper);
+
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*owner_token_a_account, false),
+        AccountMeta::new(*owner_token_b_account, false),
+        AccountMeta::new(*pda_token_a_account, false),
+        AccountMeta::new(*pda_token_b_account, false),
+        AccountMeta::new(*amm_account, false),
+        AccountMeta::new(ticks, false),
+        AccountMeta::new(position, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `DecreaseLiquidity` instruction for the position of `owner` from `tick_lower` to `tick_upper`
+#[allow(clippy::too_many_arguments)]
+pub fn decrease_liquidity(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    owner_token_a_account: &Pubkey,
+    owner_token_b_account: &Pubkey,
+    pda_token_a_account: &Pubkey,
+    pda_token_b_account: &Pubkey,
+    amm_account: &Pubkey,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u64,
+    minimum_amount_a: u64,
+    minimum_amount_b: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::DecreaseLiquidity {
+        liquidity,
+        minimum_amount_a,
+        minimum_amount_b,
+    }
+    .pack();
+    let accounts = position_accounts(
+        program_id,
+        owner,
+        owner_token_a_account,
+        owner_token_b_account,
+        pda_token_a_account,
+        pda_token_b_account,
+        amm_account,
+        tick_lower,
+        tick_upper,
+    );
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `CollectFees` instruction for the position of `owner` from `tick_lower` to `tick_upper`
+#[allow(clippy::too_many_arguments)]
+pub fn collect_fees(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    owner_token_a_account: &Pubkey,
+    owner_token_b_account: &Pubkey,
+    pda_token_a_account: &Pubkey,
+    pda_token_b_account: &Pubkey,
+    amm_account: &Pubkey,
+    tick_lower: i32,
+    tick_upper: i32,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::CollectFees {}.pack();
+    let accounts = position_accounts(
+        program_id,
+        owner,
+        owner_token_a_account,
+        owner_token_b_account,
+        pda_token_a_account,
+        pda_token_b_account,
+        amm_account,
+        tick_lower,
+        tick_upper,
+    );
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn position_accounts(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    owner_token_a_account: &Pubkey,
+    owner_token_b_account: &Pubkey,
+    pda_token_a_account: &Pubkey,
+    pda_token_b_account: &Pubkey,
+    amm_account: &Pubkey,
+    tick_lower: i32,
+    tick_upper: i32,
+) -> Vec<AccountMeta> {
+    let (pda, _bump_seed) = find_pool_authority(program_id, amm_account);
+    let (ticks, _bump_seed) = find_ticks_address(program_id, amm_account);
+    let (position, _bump_seed) =
+        find_position_address(program_id, amm_account, owner, tick_lower, tick_upper);
+
+    vec![
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*owner_token_a_account, false),
+        AccountMeta::new(*owner_token_b_account, false),
+        AccountMeta::new(*pda_token_a_account, false),
+        AccountMeta::new(*pda_token_b_account, false),
+        AccountMeta::new(*amm_account, false),
+        AccountMeta::new(ticks, false),
+        AccountMeta::new(position, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(pda, false),
+    ]
+}
+
+/// Creates an `InitOracle` instruction
+pub fn init_oracle(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    oracle_account: &Pubkey,
+    price: u128,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::InitOracle { price }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*oracle_account, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an `UpdateOracle` instruction
+pub fn update_oracle(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    oracle_account: &Pubkey,
+    price: u128,
+) -> Result<Instruction, ProgramError> {
+    let data = AMMInstruction::UpdateOracle { price }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*oracle_account, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
 }
@@ -1,7 +1,11 @@
-pub mod instruction;
+pub mod concentrated;
+pub mod curve;
 pub mod error;
+pub mod instruction;
+pub mod pmm;
 pub mod processor;
 pub mod state;
+pub mod validation;
 
 #[cfg(not(feature = "no-entrypoint"))]
-pub mod entrypoint;
\ No newline at end of file
+pub mod entrypoint;
@@ -0,0 +1,457 @@
+// inside pmm.rs
+use std::convert::TryFrom;
+
+use crate::curve::mul_div;
+
+/// Denominator of the slippage factor `k` of a proactive market maker pool: at 0 it trades at the oracle
+/// price whatever its reserves, at `SLIPPAGE_FACTOR_ONE` its curve is as steep as a constant product
+pub const SLIPPAGE_FACTOR_ONE: u64 = 1_000_000;
+
+/// One as a UQ64.64 fixed-point number
+const Q64: u128 = 1 << 64;
+
+/// Converts `amount` of token A into token B at `price`, the price of token A in token B as a UQ64.64
+/// fixed-point number, if `from_a`, and `amount` of token B into token A otherwise
+fn convert(amount: u128, price: u128, from_a: bool, round_up: bool) -> Option<u128> {
+    if from_a {
+        mul_div(amount, price, Q64, round_up)
+    } else {
+        mul_div(amount, Q64, price, round_up)
+    }
+}
+
+/// Integrates DODO's marginal price `1 - k + k * (target / reserve)^2` of a token the pool is short of,
+/// relative to the oracle price, as its reserve moves between `reserve_1` and `reserve_2`:
+/// `|reserve_1 - reserve_2| * (1 - k + k * target^2 / (reserve_1 * reserve_2))`.
+///
+/// This is what the move is worth in that token at the oracle price once the pool's premium is added.
+fn integrate(
+    target: u128,
+    reserve_1: u128,
+    reserve_2: u128,
+    slippage_factor: u64,
+    round_up: bool,
+) -> Option<u128> {
+    let delta = reserve_1.max(reserve_2) - reserve_1.min(reserve_2);
+    let premium = mul_div(
+        mul_div(delta, target, reserve_1, round_up)?,
+        target,
+        reserve_2,
+        round_up,
+    )?;
+    let k = slippage_factor as u128;
+    let one = SLIPPAGE_FACTOR_ONE as u128;
+    let numerator = delta
+        .checked_mul(one - k)?
+        .checked_add(premium.checked_mul(k)?)?;
+    if round_up {
+        numerator
+            .checked_add(one - 1)
+            .map(|numerator| numerator / one)
+    } else {
+        Some(numerator / one)
+    }
+}
+
+/// Finds the target of a token the pool holds `reserve` of, short of its target, when the pool holds
+/// `excess` of the other token above that token's target: the lowest target from which selling the token
+/// back up to it pays out the whole excess at `price`, the price of token A in token B. The token is token A
+/// if `short_a`.
+fn short_target(
+    reserve: u64,
+    excess: u64,
+    price: u128,
+    short_a: bool,
+    slippage_factor: u64,
+) -> Option<u64> {
+    let value = convert(excess as u128, price, !short_a, false)?;
+    let reserve = reserve as u128;
+    // The premium is never negative, so the target is at most `reserve + value`
+    let (mut low, mut high) = (reserve, reserve.checked_add(value)?);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        match integrate(middle, reserve, middle, slippage_factor, false) {
+            Some(integral) if integral >= value => high = middle,
+            _ => low = middle + 1,
+        }
+    }
+    u64::try_from(low).ok()
+}
+
+/// Re-derives the target of the token the pool is short of from the excess of the other token over its own
+/// target, valued at `price`, the price of token A in token B. The target of the token the pool has an
+/// excess of never moves on its own. A pool short of neither token trades at the oracle price, at its
+/// targets.
+pub fn targets(
+    reserve_a: u64,
+    reserve_b: u64,
+    target_a: u64,
+    target_b: u64,
+    price: u128,
+    slippage_factor: u64,
+) -> Option<(u64, u64)> {
+    if reserve_a < target_a {
+        let target_a = short_target(
+            reserve_a,
+            reserve_b.saturating_sub(target_b),
+            price,
+            true,
+            slippage_factor,
+        )?;
+        Some((target_a, target_b))
+    } else if reserve_b < target_b {
+        let target_b = short_target(
+            reserve_b,
+            reserve_a.saturating_sub(target_a),
+            price,
+            false,
+            slippage_factor,
+        )?;
+        Some((target_a, target_b))
+    } else {
+        Some((reserve_a, reserve_b))
+    }
+}
+
+/// Computes how many output tokens a trade of `amount_in` receives from a proactive market maker pool,
+/// selling token A if `a_to_b`, rounded down in the pool's favor. `price` is the oracle price of token A in
+/// token B as a UQ64.64 fixed-point number and the targets must be up to date, see [targets](fn.targets.html).
+///
+/// If the pool is short of the input token, the trade first sells it back to its target at a premium,
+/// which pays out the excess of the output token, then continues against the output token's side of the curve.
+#[allow(clippy::too_many_arguments)]
+pub fn swap_output(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    target_in: u64,
+    target_out: u64,
+    price: u128,
+    slippage_factor: u64,
+    a_to_b: bool,
+) -> Option<u64> {
+    let (mut amount_in, mut reserve_out) = (amount_in as u128, reserve_out as u128);
+    let (reserve_in, target_in, target_out) =
+        (reserve_in as u128, target_in as u128, target_out as u128);
+    let mut amount_out = 0;
+    if reserve_in < target_in {
+        let excess = reserve_out.checked_sub(target_out)?;
+        if amount_in < target_in - reserve_in {
+            let value = integrate(
+                target_in,
+                reserve_in,
+                reserve_in + amount_in,
+                slippage_factor,
+                false,
+            )?;
+            let amount_out = convert(value, price, a_to_b, false)?.min(excess);
+            return u64::try_from(amount_out).ok();
+        }
+        amount_in -= target_in - reserve_in;
+        amount_out = excess;
+        reserve_out = target_out;
+    }
+
+    // The pool is now short of the output token, or at its targets
+    let value = convert(amount_in, price, a_to_b, false)?;
+    let (mut low, mut high) = (0, reserve_out.saturating_sub(1));
+    while low < high {
+        let middle = low + (high - low).div_ceil(2);
+        match integrate(
+            target_out,
+            reserve_out,
+            reserve_out - middle,
+            slippage_factor,
+            true,
+        ) {
+            Some(integral) if integral <= value => low = middle,
+            _ => high = middle - 1,
+        }
+    }
+    u64::try_from(amount_out + low).ok()
+}
+
+/// Computes how many input tokens are needed to take exactly `amount_out` out of a proactive market maker pool,
+/// the inverse of [swap_output](fn.swap_output.html) rounded up in the pool's favor.
+/// `amount_out` must be strictly less than `reserve_out`.
+#[allow(clippy::too_many_arguments)]
+pub fn swap_input(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    target_in: u64,
+    target_out: u64,
+    price: u128,
+    slippage_factor: u64,
+    a_to_b: bool,
+) -> Option<u64> {
+    let (mut amount_out, mut reserve_out) = (amount_out as u128, reserve_out as u128);
+    let (reserve_in, target_in, target_out) =
+        (reserve_in as u128, target_in as u128, target_out as u128);
+    let mut amount_in = 0;
+    if reserve_in < target_in {
+        let excess = reserve_out.checked_sub(target_out)?;
+        if amount_out <= excess {
+            let (mut low, mut high) = (0, target_in - reserve_in);
+            while low < high {
+                let middle = low + (high - low) / 2;
+                let paid_out = integrate(
+                    target_in,
+                    reserve_in,
+                    reserve_in + middle,
+                    slippage_factor,
+                    false,
+                )
+                .and_then(|value| convert(value, price, a_to_b, false));
+                match paid_out {
+                    Some(paid_out) if paid_out >= amount_out => high = middle,
+                    _ => low = middle + 1,
+                }
+            }
+            return u64::try_from(low).ok();
+        }
+        amount_in = target_in - reserve_in;
+        amount_out -= excess;
+        reserve_out = target_out;
+    }
+
+    if amount_out >= reserve_out {
+        return None;
+    }
+    let value = integrate(
+        target_out,
+        reserve_out,
+        reserve_out - amount_out,
+        slippage_factor,
+        true,
+    )?;
+    let amount_in = amount_in.checked_add(convert(value, price, !a_to_b, true)?)?;
+    u64::try_from(amount_in).ok()
+}
+
+/// Computes the marginal prices of token A in token B and of token B in token A of a proactive market maker
+/// pool as UQ64.64 fixed-point numbers: the oracle price, raised by `1 - k + k * (target_a / reserve_a)^2` while
+/// the pool is short of token A and lowered by `1 - k + k * (target_b / reserve_b)^2` while it is short of token B
+pub fn spot_prices(
+    reserve_a: u64,
+    reserve_b: u64,
+    target_a: u64,
+    target_b: u64,
+    price: u128,
+    slippage_factor: u64,
+) -> (Option<u128>, Option<u128>) {
+    let multiplier = |reserve: u64, target: u64| {
+        let premium = mul_div(
+            mul_div(Q64, target as u128, reserve as u128, false)?,
+            target as u128,
+            reserve as u128,
+            false,
+        )?;
+        let k = slippage_factor as u128;
+        let one = SLIPPAGE_FACTOR_ONE as u128;
+        Q64.checked_mul(one - k)?
+            .checked_add(premium.checked_mul(k)?)
+            .map(|numerator| numerator / one)
+    };
+    let price_a = if reserve_a < target_a {
+        multiplier(reserve_a, target_a)
+            .and_then(|multiplier| mul_div(price, multiplier, Q64, false))
+    } else if reserve_b < target_b {
+        multiplier(reserve_b, target_b)
+            .and_then(|multiplier| mul_div(price, Q64, multiplier, false))
+    } else {
+        Some(price)
+    };
+    let price_b = price_a.and_then(|price_a| mul_div(Q64, Q64, price_a, false));
+    (price_a, price_b)
+}
+
+/// Reserves of a constant-product pool trading at `price_a`, to measure the price impact of a trade
+pub fn virtual_reserves(price_a: u128) -> Option<(u64, u64)> {
+    Some((1 << 32, u64::try_from(price_a >> 32).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLIPPAGE_FACTORS: [u64; 3] = [0, SLIPPAGE_FACTOR_ONE / 2, SLIPPAGE_FACTOR_ONE];
+
+    #[test]
+    fn pools_at_their_targets_keep_them() {
+        for slippage_factor in SLIPPAGE_FACTORS {
+            assert_eq!(
+                targets(1_000, 2_000, 1_000, 2_000, 2 * Q64, slippage_factor),
+                Some((1_000, 2_000))
+            );
+            assert_eq!(
+                spot_prices(1_000, 2_000, 1_000, 2_000, 2 * Q64, slippage_factor),
+                (Some(2 * Q64), Some(Q64 / 2))
+            );
+        }
+    }
+
+    #[test]
+    fn short_target_without_slippage_values_the_excess_at_the_oracle_price() {
+        assert_eq!(short_target(1_000, 2_000, 2 * Q64, true, 0), Some(2_000));
+        assert_eq!(short_target(1_000, 2_000, 2 * Q64, false, 0), Some(5_000));
+        assert_eq!(
+            short_target(1_000, 0, 2 * Q64, true, SLIPPAGE_FACTOR_ONE),
+            Some(1_000)
+        );
+    }
+
+    #[test]
+    fn short_target_pays_out_the_whole_excess() {
+        for slippage_factor in SLIPPAGE_FACTORS {
+            let target = short_target(1_000, 2_000, 2 * Q64, true, slippage_factor).unwrap();
+            assert!(target <= 2_000);
+            // Selling token A back up to its target pays out the whole excess of token B
+            assert_eq!(
+                swap_output(
+                    target - 1_000,
+                    1_000,
+                    4_000,
+                    target,
+                    2_000,
+                    2 * Q64,
+                    slippage_factor,
+                    true
+                ),
+                Some(2_000)
+            );
+        }
+    }
+
+    #[test]
+    fn swap_without_slippage_trades_at_the_oracle_price() {
+        let price = 2 * Q64;
+        assert_eq!(
+            swap_output(1_000, 1_000_000, 1_000_000, 1_000_000, 1_000_000, price, 0, true),
+            Some(2_000)
+        );
+        assert_eq!(
+            swap_output(1_000, 1_000_000, 1_000_000, 1_000_000, 1_000_000, price, 0, false),
+            Some(500)
+        );
+        assert_eq!(
+            swap_input(2_000, 1_000_000, 1_000_000, 1_000_000, 1_000_000, price, 0, true),
+            Some(1_000)
+        );
+        assert_eq!(
+            swap_input(500, 1_000_000, 1_000_000, 1_000_000, 1_000_000, price, 0, false),
+            Some(1_000)
+        );
+    }
+
+    #[test]
+    fn swap_with_full_slippage_is_a_constant_product_at_the_targets() {
+        // At the oracle price, a pool at its targets trades like a constant-product pool of its reserves
+        let output = swap_output(
+            1_000_000,
+            1_000_000,
+            1_000_000,
+            1_000_000,
+            1_000_000,
+            Q64,
+            SLIPPAGE_FACTOR_ONE,
+            true,
+        );
+        assert!(output.unwrap().abs_diff(500_000) <= 1);
+    }
+
+    #[test]
+    fn swap_output_cannot_drain_the_pool() {
+        for slippage_factor in SLIPPAGE_FACTORS {
+            for a_to_b in [true, false] {
+                let output = swap_output(
+                    u64::MAX / 4,
+                    1_000,
+                    1_000,
+                    1_000,
+                    1_000,
+                    Q64,
+                    slippage_factor,
+                    a_to_b,
+                )
+                .unwrap();
+                assert!(output < 1_000);
+                assert_eq!(
+                    swap_input(
+                        1_000,
+                        1_000,
+                        1_000,
+                        1_000,
+                        1_000,
+                        Q64,
+                        slippage_factor,
+                        a_to_b
+                    ),
+                    None
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn swap_input_and_output_round_trip_in_the_pools_favor() {
+        // Pools at their targets and pools short of the input token, across its target
+        let pools = [
+            (1_000_000, 1_000_000, 1_000_000, 1_000_000),
+            (600_000, 1_400_000, 1_000_000, 1_000_000),
+        ];
+        for slippage_factor in SLIPPAGE_FACTORS {
+            for (reserve_in, reserve_out, target_in, target_out) in pools {
+                for amount in [1, 1_000, 300_000, 500_000, 900_000] {
+                    let price = 3 * Q64 / 2;
+                    let output = swap_output(
+                        amount,
+                        reserve_in,
+                        reserve_out,
+                        target_in,
+                        target_out,
+                        price,
+                        slippage_factor,
+                        true,
+                    )
+                    .unwrap();
+                    let input = swap_input(
+                        output,
+                        reserve_in,
+                        reserve_out,
+                        target_in,
+                        target_out,
+                        price,
+                        slippage_factor,
+                        true,
+                    )
+                    .unwrap();
+                    assert!(input <= amount);
+                    if let Some(input) = swap_input(
+                        amount,
+                        reserve_in,
+                        reserve_out,
+                        target_in,
+                        target_out,
+                        price,
+                        slippage_factor,
+                        true,
+                    ) {
+                        let output = swap_output(
+                            input,
+                            reserve_in,
+                            reserve_out,
+                            target_in,
+                            target_out,
+                            price,
+                            slippage_factor,
+                            true,
+                        )
+                        .unwrap();
+                        assert!(output >= amount);
+                    }
+                }
+            }
+        }
+    }
+}
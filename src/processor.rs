@@ -2,16 +2,37 @@ use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
     msg,
-    program::{invoke, invoke_signed},
+    program::{invoke, invoke_signed, set_return_data},
     program_error::ProgramError,
     program_pack::{IsInitialized, Pack},
     pubkey::Pubkey,
-    sysvar::{rent::Rent, Sysvar},
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
 };
-
 use spl_token::state::Account as TokenAccount;
+use std::convert::TryFrom;
+
+use crate::{
+    concentrated,
+    curve::{self, CurveType},
+    error::AMMError,
+    instruction::{self, AMMInstruction},
+    pmm,
+    state::{Observation, Observations, Oracle, Position, Tick, Ticks, AMM},
+    validation,
+};
 
-use crate::{error::AMMError, instruction::AMMInstruction, state::AMM};
+/// A trade of either an exact input or an exact output, along with its slippage limit
+enum Trade {
+    ExactIn {
+        amount_in: u64,
+        minimum_amount_out: u64,
+    },
+    ExactOut {
+        amount_out: u64,
+        maximum_amount_in: u64,
+    },
+}
 
 pub struct Processor;
 impl Processor {
@@ -23,120 +44,520 @@ impl Processor {
         let instruction = AMMInstruction::unpack(instruction_data)?;
 
         match instruction {
-            AMMInstruction::InitAMM {  } => {
+            AMMInstruction::InitAMM {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                protocol_fee_numerator,
+                initial_amount_a,
+                initial_amount_b,
+                curve_type,
+                amplification,
+                weight_a,
+                end_weight_a,
+                start_timestamp,
+                end_timestamp,
+                tick_spacing,
+                slippage_factor,
+                max_oracle_staleness,
+                max_price_deviation_bps,
+            } => {
                 msg!("Instruction: InitAMM");
-                Self::process_init_amm(accounts, program_id)
+                Self::process_init_amm(
+                    accounts,
+                    trade_fee_numerator,
+                    trade_fee_denominator,
+                    protocol_fee_numerator,
+                    initial_amount_a,
+                    initial_amount_b,
+                    curve_type,
+                    amplification,
+                    weight_a,
+                    end_weight_a,
+                    start_timestamp,
+                    end_timestamp,
+                    tick_spacing,
+                    slippage_factor,
+                    max_oracle_staleness,
+                    max_price_deviation_bps,
+                    program_id,
+                )
             }
-            AMMInstruction::ProvLiquidity { busd_amount, bstock_amount } => {
+            AMMInstruction::ProvLiquidity { amount_a, amount_b } => {
                 msg!("Instruction: ProvLiquidity");
-                Self::process_provide_liquidity(accounts, busd_amount, bstock_amount, program_id)
+                Self::process_provide_liquidity(accounts, amount_a, amount_b, program_id)
             }
             AMMInstruction::ClaimLiquidity { amount } => {
                 msg!("Instruction: ClaimLiquidity");
                 Self::process_claim_liquidity(accounts, amount, program_id)
             }
-            AMMInstruction::TradeBUSD { amount } => {
-                msg!("Instruction: TradeBUSD");
-                Self::process_trade_busd(accounts, amount, program_id)
+            AMMInstruction::Swap {
+                amount_in,
+                minimum_amount_out,
+            } => {
+                msg!("Instruction: Swap");
+                Self::process_swap(accounts, amount_in, minimum_amount_out, program_id)
             }
-            AMMInstruction::TradebStock { amount } => {
-                msg!("Instruction: TradebStock");
-                Self::process_trade_bstock(accounts, amount, program_id)
+            AMMInstruction::SwapExactOut {
+                amount_out,
+                maximum_amount_in,
+            } => {
+                msg!("Instruction: SwapExactOut");
+                Self::process_swap_exact_out(accounts, amount_out, maximum_amount_in, program_id)
             }
             AMMInstruction::CloseAMM {} => {
                 msg!("Instruction: CloseAMM");
                 Self::process_close_amm(accounts, program_id)
             }
+            AMMInstruction::WithdrawProtocolFees {} => {
+                msg!("Instruction: WithdrawProtocolFees");
+                Self::process_withdraw_protocol_fees(accounts, program_id)
+            }
+            AMMInstruction::Sync {} => {
+                msg!("Instruction: Sync");
+                Self::process_sync(accounts, program_id)
+            }
+            AMMInstruction::Skim {} => {
+                msg!("Instruction: Skim");
+                Self::process_skim(accounts, program_id)
+            }
+            AMMInstruction::InitObservations {} => {
+                msg!("Instruction: InitObservations");
+                Self::process_init_observations(accounts, program_id)
+            }
+            AMMInstruction::QueryTwap { window_seconds } => {
+                msg!("Instruction: QueryTwap");
+                Self::process_query_twap(accounts, window_seconds, program_id)
+            }
+            AMMInstruction::Quote { amount_in, a_to_b } => {
+                msg!("Instruction: Quote");
+                Self::process_quote(accounts, amount_in, a_to_b, program_id)
+            }
+            AMMInstruction::RampAmplification {
+                target_amplification,
+                end_timestamp,
+            } => {
+                msg!("Instruction: RampAmplification");
+                Self::process_ramp_amplification(
+                    accounts,
+                    target_amplification,
+                    end_timestamp,
+                    program_id,
+                )
+            }
+            AMMInstruction::StopRamp {} => {
+                msg!("Instruction: StopRamp");
+                Self::process_stop_ramp(accounts, program_id)
+            }
+            AMMInstruction::InitTicks {} => {
+                msg!("Instruction: InitTicks");
+                Self::process_init_ticks(accounts, program_id)
+            }
+            AMMInstruction::IncreaseLiquidity {
+                tick_lower,
+                tick_upper,
+                liquidity,
+                maximum_amount_a,
+                maximum_amount_b,
+            } => {
+                msg!("Instruction: IncreaseLiquidity");
+                Self::process_increase_liquidity(
+                    accounts,
+                    tick_lower,
+                    tick_upper,
+                    liquidity,
+                    maximum_amount_a,
+                    maximum_amount_b,
+                    program_id,
+                )
+            }
+            AMMInstruction::DecreaseLiquidity {
+                liquidity,
+                minimum_amount_a,
+                minimum_amount_b,
+            } => {
+                msg!("Instruction: DecreaseLiquidity");
+                Self::process_decrease_liquidity(
+                    accounts,
+                    liquidity,
+                    minimum_amount_a,
+                    minimum_amount_b,
+                    program_id,
+                )
+            }
+            AMMInstruction::CollectFees {} => {
+                msg!("Instruction: CollectFees");
+                Self::process_collect_fees(accounts, program_id)
+            }
+            AMMInstruction::InitOracle { price } => {
+                msg!("Instruction: InitOracle");
+                Self::process_init_oracle(accounts, price, program_id)
+            }
+            AMMInstruction::UpdateOracle { price } => {
+                msg!("Instruction: UpdateOracle");
+                Self::process_update_oracle(accounts, price, program_id)
+            }
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn process_init_amm(
         accounts: &[AccountInfo],
+        trade_fee_numerator: u64,
+        trade_fee_denominator: u64,
+        protocol_fee_numerator: u64,
+        initial_amount_a: u64,
+        initial_amount_b: u64,
+        curve_type: CurveType,
+        amplification: u64,
+        weight_a: u64,
+        end_weight_a: u64,
+        start_timestamp: i64,
+        end_timestamp: i64,
+        tick_spacing: u16,
+        slippage_factor: u64,
+        max_oracle_staleness: u64,
+        max_price_deviation_bps: u64,
         program_id: &Pubkey,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let initializer_account = next_account_info(account_info_iter)?;
+        validation::check_signer(initializer_account)?;
 
-        if !initializer_account.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
+        let total_fee_numerator = trade_fee_numerator
+            .checked_add(protocol_fee_numerator)
+            .ok_or(AMMError::InvalidFee)?;
+        if trade_fee_denominator == 0 || total_fee_numerator >= trade_fee_denominator {
+            return Err(AMMError::InvalidFee.into());
+        }
+        let valid_amplification = match curve_type {
+            CurveType::ConstantProduct
+            | CurveType::Weighted
+            | CurveType::LiquidityBootstrapping
+            | CurveType::Concentrated
+            | CurveType::ProactiveMarketMaker => amplification == 0,
+            CurveType::StableSwap => {
+                (curve::MIN_AMPLIFICATION..=curve::MAX_AMPLIFICATION).contains(&amplification)
+            }
+        };
+        if !valid_amplification {
+            return Err(AMMError::InvalidAmplification.into());
+        }
+        let weight_range = curve::MIN_WEIGHT..=curve::WEIGHT_ONE - curve::MIN_WEIGHT;
+        let valid_weight = match curve_type {
+            CurveType::ConstantProduct
+            | CurveType::StableSwap
+            | CurveType::Concentrated
+            | CurveType::ProactiveMarketMaker => {
+                weight_a == curve::WEIGHT_ONE / 2 && end_weight_a == 0
+            }
+            CurveType::Weighted => weight_range.contains(&weight_a) && end_weight_a == 0,
+            CurveType::LiquidityBootstrapping => {
+                weight_range.contains(&weight_a) && weight_range.contains(&end_weight_a)
+            }
+        };
+        if !valid_weight {
+            return Err(AMMError::InvalidWeight.into());
+        }
+        let now = Clock::get()?.unix_timestamp;
+        let valid_schedule = match curve_type {
+            CurveType::LiquidityBootstrapping => {
+                start_timestamp < end_timestamp && now < end_timestamp
+            }
+            _ => start_timestamp == 0 && end_timestamp == 0,
+        };
+        if !valid_schedule {
+            return Err(AMMError::InvalidRamp.into());
+        }
+        let valid_tick_spacing = match curve_type {
+            CurveType::Concentrated => (1..=concentrated::MAX_TICK_SPACING).contains(&tick_spacing),
+            _ => tick_spacing == 0,
+        };
+        if !valid_tick_spacing {
+            return Err(AMMError::InvalidTickSpacing.into());
+        }
+        let valid_slippage_factor = match curve_type {
+            CurveType::ProactiveMarketMaker => slippage_factor <= pmm::SLIPPAGE_FACTOR_ONE,
+            _ => slippage_factor == 0,
+        };
+        if !valid_slippage_factor {
+            return Err(AMMError::InvalidSlippageFactor.into());
+        }
+        let has_oracle =
+            curve_type == CurveType::ProactiveMarketMaker || max_price_deviation_bps > 0;
+        let valid_oracle_staleness = if has_oracle {
+            max_oracle_staleness > 0
+        } else {
+            max_oracle_staleness == 0
+        };
+        if !valid_oracle_staleness {
+            return Err(AMMError::InvalidOracleStaleness.into());
         }
 
-        let temp_busd_token_account = next_account_info(account_info_iter)?;
-        let temp_bstock_token_account = next_account_info(account_info_iter)?;
-        let temp_bstocklqdy_token_account = next_account_info(account_info_iter)?;
+        let pda_token_a_account = next_account_info(account_info_iter)?;
+        let pda_token_b_account = next_account_info(account_info_iter)?;
+        let lp_mint = next_account_info(account_info_iter)?;
 
         let amm_account = next_account_info(account_info_iter)?;
-        let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+        let rent_account = next_account_info(account_info_iter)?;
+        let rent = &Rent::from_account_info(rent_account)?;
 
         if !rent.is_exempt(amm_account.lamports(), amm_account.data_len()) {
             return Err(AMMError::NotRentExempt.into());
         }
 
+        let token_program = next_account_info(account_info_iter)?;
+        let fee_authority_account = next_account_info(account_info_iter)?;
+        let initializer_token_a_account = next_account_info(account_info_iter)?;
+        let initializer_token_b_account = next_account_info(account_info_iter)?;
+        let initializer_lp_token_account = next_account_info(account_info_iter)?;
+        let pda_account = next_account_info(account_info_iter)?;
+        let mint_a = next_account_info(account_info_iter)?;
+        let mint_b = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let oracle_account = next_account_info(account_info_iter).ok();
+        validation::check_token_program(token_program)?;
+        validation::check_system_program(system_program)?;
+        validation::check_amm_owner(amm_account, program_id)?;
+        validation::check_distinct(&[
+            pda_token_a_account,
+            pda_token_b_account,
+            lp_mint,
+            amm_account,
+            initializer_token_a_account,
+            initializer_token_b_account,
+            initializer_lp_token_account,
+            mint_a,
+            mint_b,
+        ])?;
+
         let mut amm_state = AMM::unpack_unchecked(&amm_account.data.borrow())?;
         if amm_state.is_initialized() {
             return Err(ProgramError::AccountAlreadyInitialized);
         }
 
+        validation::unpack_mint(mint_a)?;
+        validation::unpack_mint(mint_b)?;
+        let lp_mint_info = validation::unpack_mint(lp_mint)?;
+        if lp_mint_info.supply != 0 {
+            return Err(AMMError::InvalidLPSupply.into());
+        }
+        if lp_mint_info.freeze_authority.is_some() {
+            return Err(AMMError::InvalidFreezeAuthority.into());
+        }
+        validation::unpack_token_account_with_mint(initializer_token_a_account, mint_a.key)?;
+        validation::unpack_token_account_with_mint(initializer_token_b_account, mint_b.key)?;
+        validation::unpack_token_account_with_mint(initializer_lp_token_account, lp_mint.key)?;
+
+        amm_state.curve_type = curve_type;
+        amm_state.amplification = amplification;
+        amm_state.weight_a = weight_a;
+        amm_state.weight_b = curve::WEIGHT_ONE - weight_a;
+        amm_state.start_weight_a = weight_a;
+        amm_state.end_weight_a = if curve_type == CurveType::LiquidityBootstrapping {
+            end_weight_a
+        } else {
+            weight_a
+        };
+        amm_state.weights_start_timestamp = start_timestamp;
+        amm_state.weights_end_timestamp = end_timestamp;
+        amm_state.tick_spacing = tick_spacing;
+        amm_state.liquidity = 0;
+        amm_state.fee_growth_global_a = 0;
+        amm_state.fee_growth_global_b = 0;
+        amm_state.max_oracle_staleness = max_oracle_staleness;
+        amm_state.max_price_deviation_bps = max_price_deviation_bps;
+        if has_oracle {
+            let oracle_account = oracle_account.ok_or(AMMError::InvalidOracleAccount)?;
+            amm_state.oracle_pubkey = *oracle_account.key;
+            amm_state.oracle_price =
+                Self::oracle_price(program_id, &amm_state, Some(oracle_account), now)?;
+        } else {
+            amm_state.oracle_pubkey = Pubkey::default();
+            amm_state.oracle_price = 0;
+        }
+        if curve_type == CurveType::ProactiveMarketMaker {
+            amm_state.target_a = initial_amount_a;
+            amm_state.target_b = initial_amount_b;
+        } else {
+            amm_state.target_a = 0;
+            amm_state.target_b = 0;
+        }
+        amm_state.slippage_factor = slippage_factor;
+        let (reserve_a, reserve_b, lp_amount) = if curve_type == CurveType::Concentrated {
+            let sqrt_price = concentrated::initial_sqrt_price(initial_amount_a, initial_amount_b)
+                .ok_or(AMMError::InvalidRatio)?;
+            amm_state.tick_current =
+                concentrated::tick_at_sqrt_price(sqrt_price).ok_or(AMMError::InvalidRatio)?;
+            amm_state.sqrt_price = sqrt_price;
+            (0, 0, 0)
+        } else {
+            let lp_amount = amm_state
+                .initial_liquidity(initial_amount_a, initial_amount_b)
+                .ok_or(AMMError::AmountOverflow)?;
+            if lp_amount == 0 {
+                return Err(AMMError::InsufficientLiquidity.into());
+            }
+            (initial_amount_a, initial_amount_b, lp_amount)
+        };
+
+        let (pda, bump_seed) = instruction::find_pool_authority(program_id, amm_account.key);
+        validation::check_pool_authority(pda_account, &pda)?;
+        let (vault_a, vault_a_bump_seed) =
+            instruction::find_pool_vault(program_id, amm_account.key, mint_a.key);
+        let (vault_b, vault_b_bump_seed) =
+            instruction::find_pool_vault(program_id, amm_account.key, mint_b.key);
+        if *pda_token_a_account.key != vault_a || *pda_token_b_account.key != vault_b {
+            return Err(AMMError::IncorrectPoolTokenAccount.into());
+        }
+
         amm_state.is_initialized = true;
         amm_state.initializer_account_pubkey = *initializer_account.key;
-        amm_state.busd_token_account_pubkey = *temp_busd_token_account.key;
-        amm_state.bstock_token_account_pubkey = *temp_bstock_token_account.key;
-        amm_state.bstocklqdy_token_account_pubkey = *temp_bstocklqdy_token_account.key;
+        amm_state.token_a_account_pubkey = vault_a;
+        amm_state.token_b_account_pubkey = vault_b;
+        amm_state.lp_mint_pubkey = *lp_mint.key;
+        amm_state.mint_a_pubkey = *mint_a.key;
+        amm_state.mint_b_pubkey = *mint_b.key;
+        amm_state.trade_fee_numerator = trade_fee_numerator;
+        amm_state.trade_fee_denominator = trade_fee_denominator;
+        amm_state.protocol_fee_numerator = protocol_fee_numerator;
+        amm_state.fee_authority_pubkey = *fee_authority_account.key;
+        amm_state.protocol_fees_a = 0;
+        amm_state.protocol_fees_b = 0;
+        amm_state.bump_seed = bump_seed;
+        amm_state.reserve_a = reserve_a;
+        amm_state.reserve_b = reserve_b;
+        amm_state.price_a_cumulative = 0;
+        amm_state.price_b_cumulative = 0;
+        amm_state.last_update_timestamp = now;
+        amm_state.ramp_start_amplification = amplification;
+        amm_state.target_amplification = amplification;
+        amm_state.ramp_start_timestamp = amm_state.last_update_timestamp;
+        amm_state.ramp_end_timestamp = amm_state.last_update_timestamp;
 
         AMM::pack(amm_state, &mut amm_account.data.borrow_mut())?;
-        let (pda, _bump_seed) = Pubkey::find_program_address(&[b"bravv"], program_id);
 
-        let token_program = next_account_info(account_info_iter)?;
+        msg!("Calling the system program to create the pool's token accounts...");
+        for (vault_account, mint, vault_bump_seed) in [
+            (pda_token_a_account, mint_a, vault_a_bump_seed),
+            (pda_token_b_account, mint_b, vault_b_bump_seed),
+        ] {
+            let create_vault_ix = system_instruction::create_account(
+                initializer_account.key,
+                vault_account.key,
+                rent.minimum_balance(TokenAccount::LEN),
+                TokenAccount::LEN as u64,
+                token_program.key,
+            );
+            invoke_signed(
+                &create_vault_ix,
+                &[
+                    initializer_account.clone(),
+                    vault_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[
+                    &b"bravv"[..],
+                    amm_account.key.as_ref(),
+                    mint.key.as_ref(),
+                    &[vault_bump_seed],
+                ]],
+            )?;
+
+            let initialize_vault_ix = spl_token::instruction::initialize_account(
+                token_program.key,
+                vault_account.key,
+                mint.key,
+                &pda,
+            )?;
+            invoke(
+                &initialize_vault_ix,
+                &[
+                    vault_account.clone(),
+                    mint.clone(),
+                    pda_account.clone(),
+                    rent_account.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+        }
 
-        let busd_owner_change_ix = spl_token::instruction::set_authority(
+        let lp_mint_authority_change_ix = spl_token::instruction::set_authority(
             token_program.key,
-            temp_busd_token_account.key,
+            lp_mint.key,
             Some(&pda),
-            spl_token::instruction::AuthorityType::AccountOwner,
+            spl_token::instruction::AuthorityType::MintTokens,
             initializer_account.key,
-            &[&initializer_account.key],
+            &[initializer_account.key],
         )?;
-        let bstock_owner_change_ix = spl_token::instruction::set_authority(
+        msg!("Calling the token program to transfer LP mint authority...");
+        invoke(
+            &lp_mint_authority_change_ix,
+            &[
+                lp_mint.clone(),
+                initializer_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        if curve_type == CurveType::Concentrated {
+            // The pool is funded by its positions instead, see `IncreaseLiquidity`
+            return Ok(());
+        }
+
+        let transfer_token_a_to_amm = spl_token::instruction::transfer(
             token_program.key,
-            temp_busd_token_account.key,
-            Some(&pda),
-            spl_token::instruction::AuthorityType::AccountOwner,
+            initializer_token_a_account.key,
+            pda_token_a_account.key,
             initializer_account.key,
-            &[&initializer_account.key],
+            &[initializer_account.key],
+            initial_amount_a,
         )?;
-        let bstocklqdy_owner_change_ix = spl_token::instruction::set_authority(
+        let transfer_token_b_to_amm = spl_token::instruction::transfer(
             token_program.key,
-            temp_busd_token_account.key,
-            Some(&pda),
-            spl_token::instruction::AuthorityType::AccountOwner,
+            initializer_token_b_account.key,
+            pda_token_b_account.key,
             initializer_account.key,
-            &[&initializer_account.key],
+            &[initializer_account.key],
+            initial_amount_b,
         )?;
-
-        msg!("Calling the token program to transfer token account ownership...");
+        msg!("Calling the token program to transfer the initial liquidity to the AMM...");
         invoke(
-            &busd_owner_change_ix,
+            &transfer_token_a_to_amm,
             &[
-                temp_busd_token_account.clone(),
+                initializer_token_a_account.clone(),
+                pda_token_a_account.clone(),
                 initializer_account.clone(),
                 token_program.clone(),
             ],
         )?;
         invoke(
-            &bstock_owner_change_ix,
+            &transfer_token_b_to_amm,
             &[
-                temp_busd_token_account.clone(),
+                initializer_token_b_account.clone(),
+                pda_token_b_account.clone(),
                 initializer_account.clone(),
                 token_program.clone(),
             ],
         )?;
-        invoke(
-            &bstocklqdy_owner_change_ix,
+
+        let mint_lp_to_initializer = spl_token::instruction::mint_to(
+            token_program.key,
+            lp_mint.key,
+            initializer_lp_token_account.key,
+            &pda,
+            &[&pda],
+            lp_amount,
+        )?;
+        msg!("Calling the token program to mint the initial LP tokens to the initializer...");
+        invoke_signed(
+            &mint_lp_to_initializer,
             &[
-                temp_busd_token_account.clone(),
-                initializer_account.clone(),
+                lp_mint.clone(),
+                initializer_lp_token_account.clone(),
+                pda_account.clone(),
                 token_program.clone(),
             ],
+            &[&[&b"bravv"[..], amm_account.key.as_ref(), &[bump_seed]]],
         )?;
 
         Ok(())
@@ -144,102 +565,149 @@ impl Processor {
 
     fn process_provide_liquidity(
         accounts: &[AccountInfo],
-        busd_amount: u64,
-        bstock_amount: u64,
+        amount_a: u64,
+        amount_b: u64,
         program_id: &Pubkey,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-        
+
         let liquidity_provider_account = next_account_info(account_info_iter)?;
-        if !liquidity_provider_account.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-        let liquidity_provider_bstocklqdy_token_account = next_account_info(account_info_iter)?;
-        let liquidity_provider_busd_token_account = next_account_info(account_info_iter)?;
-        let liquidity_provider_bstock_token_account = next_account_info(account_info_iter)?;
-
-        let pda_busd_token_account = next_account_info(account_info_iter)?;
-        let pda_bstock_token_account = next_account_info(account_info_iter)?;
-        let pda_bstocklqdy_token_account = next_account_info(account_info_iter)?;
-
-        let pda_busd_token_account_info =
-            TokenAccount::unpack(&pda_busd_token_account.data.borrow())?;
-        let pda_bstock_token_account_info =
-            TokenAccount::unpack(&pda_bstock_token_account.data.borrow())?;
-        // let pda_bstocklqdy_token_account_info =
-        //     TokenAccount::unpack(&pda_bstocklqdy_token_account.data.borrow())?;
-
-        let amm_ratio = pda_busd_token_account_info.amount / pda_bstock_token_account_info.amount;
-        let provider_ratio = bstock_amount / busd_amount;
-        
-        if amm_ratio != provider_ratio {
-            return Err(AMMError::InvalidRatio.into());
-        }
+        validation::check_signer(liquidity_provider_account)?;
+        let liquidity_provider_token_a_account = next_account_info(account_info_iter)?;
+        let liquidity_provider_token_b_account = next_account_info(account_info_iter)?;
+        let liquidity_provider_lp_token_account = next_account_info(account_info_iter)?;
 
-        let (pda, bump_seed) = Pubkey::find_program_address(&[b"bravv"], program_id);
+        let pda_token_a_account = next_account_info(account_info_iter)?;
+        let pda_token_b_account = next_account_info(account_info_iter)?;
+        let lp_mint = next_account_info(account_info_iter)?;
 
         let amm_account = next_account_info(account_info_iter)?;
-        let amm_info = AMM::unpack(&amm_account.data.borrow())?;
-        if amm_info.busd_token_account_pubkey != *pda_busd_token_account.key || amm_info.bstock_token_account_pubkey != *pda_bstock_token_account.key || amm_info.bstocklqdy_token_account_pubkey != *pda_bstocklqdy_token_account.key {
-            return Err(ProgramError::InvalidAccountData);
-        }
         let token_program = next_account_info(account_info_iter)?;
-        let transfer_busd_to_amm = spl_token::instruction::transfer(
+        let pda_account = next_account_info(account_info_iter)?;
+        let observations_account = next_account_info(account_info_iter).ok();
+
+        let mut amm_info = validation::unpack_amm(amm_account, program_id)?;
+        if amm_info.curve_type == CurveType::Concentrated {
+            return Err(AMMError::InvalidCurveType.into());
+        }
+        validation::check_pool_token_accounts(&amm_info, pda_token_a_account, pda_token_b_account)?;
+        validation::check_pool_mint(&amm_info, lp_mint)?;
+        validation::check_token_program(token_program)?;
+        validation::check_distinct(&[
+            liquidity_provider_token_a_account,
+            liquidity_provider_token_b_account,
+            liquidity_provider_lp_token_account,
+            pda_token_a_account,
+            pda_token_b_account,
+            lp_mint,
+            amm_account,
+        ])?;
+        validation::unpack_token_account_with_mint(
+            liquidity_provider_token_a_account,
+            &amm_info.mint_a_pubkey,
+        )?;
+        validation::unpack_token_account_with_mint(
+            liquidity_provider_token_b_account,
+            &amm_info.mint_b_pubkey,
+        )?;
+        validation::unpack_token_account_with_mint(
+            liquidity_provider_lp_token_account,
+            &amm_info.lp_mint_pubkey,
+        )?;
+
+        let bump_seed = amm_info.bump_seed;
+        let pda = Self::pool_authority(program_id, amm_account.key, bump_seed)?;
+        validation::check_pool_authority(pda_account, &pda)?;
+
+        amm_info.update_price_accumulators(Clock::get()?.unix_timestamp);
+        Self::record_observation(program_id, amm_account, &amm_info, observations_account)?;
+        let (reserve_a, reserve_b) = (amm_info.reserve_a, amm_info.reserve_b);
+        let lp_mint_info = validation::unpack_mint(lp_mint)?;
+
+        let lp_amount = curve::deposit_liquidity(
+            amount_a,
+            amount_b,
+            reserve_a,
+            reserve_b,
+            lp_mint_info.supply,
+        )
+        .ok_or(AMMError::AmountOverflow)?;
+        if lp_amount == 0 {
+            return Err(AMMError::InsufficientLiquidity.into());
+        }
+        let (amount_a, amount_b) =
+            curve::deposit_amounts(lp_amount, reserve_a, reserve_b, lp_mint_info.supply)
+                .ok_or(AMMError::AmountOverflow)?;
+
+        let reserve_a = reserve_a
+            .checked_add(amount_a)
+            .ok_or(AMMError::AmountOverflow)?;
+        let reserve_b = reserve_b
+            .checked_add(amount_b)
+            .ok_or(AMMError::AmountOverflow)?;
+        if amm_info.curve_type == CurveType::ProactiveMarketMaker {
+            amm_info
+                .scale_targets(reserve_a, reserve_b)
+                .ok_or(AMMError::AmountOverflow)?;
+        }
+        amm_info.reserve_a = reserve_a;
+        amm_info.reserve_b = reserve_b;
+        AMM::pack(amm_info, &mut amm_account.data.borrow_mut())?;
+
+        let transfer_token_a_to_amm = spl_token::instruction::transfer(
             token_program.key,
-            liquidity_provider_busd_token_account.key,
-            pda_busd_token_account.key,
+            liquidity_provider_token_a_account.key,
+            pda_token_a_account.key,
             liquidity_provider_account.key,
-            &[&liquidity_provider_account.key],
-            busd_amount,
+            &[liquidity_provider_account.key],
+            amount_a,
         )?;
-        let transfer_bstock_to_amm = spl_token::instruction::transfer(
+        let transfer_token_b_to_amm = spl_token::instruction::transfer(
             token_program.key,
-            liquidity_provider_bstock_token_account.key,
-            pda_bstock_token_account.key,
+            liquidity_provider_token_b_account.key,
+            pda_token_b_account.key,
             liquidity_provider_account.key,
-            &[&liquidity_provider_account.key],
-            bstock_amount,
+            &[liquidity_provider_account.key],
+            amount_b,
         )?;
         msg!("Calling the token program to transfer tokens to the AMM...");
         invoke(
-            &transfer_busd_to_amm,
+            &transfer_token_a_to_amm,
             &[
-                liquidity_provider_busd_token_account.clone(),
-                pda_busd_token_account.clone(),
+                liquidity_provider_token_a_account.clone(),
+                pda_token_a_account.clone(),
                 liquidity_provider_account.clone(),
                 token_program.clone(),
             ],
         )?;
         invoke(
-            &transfer_bstock_to_amm,
+            &transfer_token_b_to_amm,
             &[
-                liquidity_provider_bstock_token_account.clone(),
-                pda_bstock_token_account.clone(),
+                liquidity_provider_token_b_account.clone(),
+                pda_token_b_account.clone(),
                 liquidity_provider_account.clone(),
                 token_program.clone(),
             ],
         )?;
 
-        let pda_account = next_account_info(account_info_iter)?;
-
-        let transfer_bstocklqdy_to_liquidity_provider = spl_token::instruction::transfer(
+        let mint_lp_to_liquidity_provider = spl_token::instruction::mint_to(
             token_program.key,
-            pda_bstocklqdy_token_account.key,
-            liquidity_provider_bstocklqdy_token_account.key,
+            lp_mint.key,
+            liquidity_provider_lp_token_account.key,
             &pda,
             &[&pda],
-            busd_amount,
+            lp_amount,
         )?;
-        msg!("Calling the token program to transfer bStockLQDY to the liquidity provider...");
+        msg!("Calling the token program to mint LP tokens to the liquidity provider...");
         invoke_signed(
-            &transfer_bstocklqdy_to_liquidity_provider,
+            &mint_lp_to_liquidity_provider,
             &[
-                pda_bstocklqdy_token_account.clone(),
-                liquidity_provider_bstocklqdy_token_account.clone(),
+                lp_mint.clone(),
+                liquidity_provider_lp_token_account.clone(),
                 pda_account.clone(),
                 token_program.clone(),
             ],
-            &[&[&b"bravv"[..], &[bump_seed]]],
+            &[&[&b"bravv"[..], amm_account.key.as_ref(), &[bump_seed]]],
         )?;
 
         Ok(())
@@ -247,343 +715,1846 @@ impl Processor {
 
     fn process_claim_liquidity(
         accounts: &[AccountInfo],
-        bstocklqdy_amount: u64,
+        lp_amount: u64,
         program_id: &Pubkey,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-        
+
         let liquidity_provider_account = next_account_info(account_info_iter)?;
-        if !liquidity_provider_account.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-        let liquidity_provider_busd_token_account = next_account_info(account_info_iter)?;
-        let liquidity_provider_bstock_token_account = next_account_info(account_info_iter)?;
-        let liquidity_provider_bstocklqdy_token_account = next_account_info(account_info_iter)?;
+        validation::check_signer(liquidity_provider_account)?;
+        let liquidity_provider_token_a_account = next_account_info(account_info_iter)?;
+        let liquidity_provider_token_b_account = next_account_info(account_info_iter)?;
+        let liquidity_provider_lp_token_account = next_account_info(account_info_iter)?;
 
-        let pda_busd_token_account = next_account_info(account_info_iter)?;
-        let pda_bstock_token_account = next_account_info(account_info_iter)?;
-        let pda_bstocklqdy_token_account = next_account_info(account_info_iter)?;
+        let pda_token_a_account = next_account_info(account_info_iter)?;
+        let pda_token_b_account = next_account_info(account_info_iter)?;
+        let lp_mint = next_account_info(account_info_iter)?;
 
-        let pda_busd_token_account_info =
-            TokenAccount::unpack(&pda_busd_token_account.data.borrow())?;
-        let pda_bstock_token_account_info =
-            TokenAccount::unpack(&pda_bstock_token_account.data.borrow())?;
-        // let pda_bstocklqdy_token_account_info =
-        //     TokenAccount::unpack(&pda_bstocklqdy_token_account.data.borrow())?;
+        let amm_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let pda_account = next_account_info(account_info_iter)?;
+        let observations_account = next_account_info(account_info_iter).ok();
 
-        let amm_ratio = pda_busd_token_account_info.amount / pda_bstock_token_account_info.amount;
-        let busd_amount = bstocklqdy_amount;
-        let bstock_amount = busd_amount * amm_ratio;
+        let mut amm_info = validation::unpack_amm(amm_account, program_id)?;
+        if amm_info.curve_type == CurveType::Concentrated {
+            return Err(AMMError::InvalidCurveType.into());
+        }
+        validation::check_pool_token_accounts(&amm_info, pda_token_a_account, pda_token_b_account)?;
+        validation::check_pool_mint(&amm_info, lp_mint)?;
+        validation::check_token_program(token_program)?;
+        validation::check_distinct(&[
+            liquidity_provider_token_a_account,
+            liquidity_provider_token_b_account,
+            liquidity_provider_lp_token_account,
+            pda_token_a_account,
+            pda_token_b_account,
+            lp_mint,
+            amm_account,
+        ])?;
+        validation::unpack_token_account_with_mint(
+            liquidity_provider_token_a_account,
+            &amm_info.mint_a_pubkey,
+        )?;
+        validation::unpack_token_account_with_mint(
+            liquidity_provider_token_b_account,
+            &amm_info.mint_b_pubkey,
+        )?;
+        validation::unpack_token_account_with_mint(
+            liquidity_provider_lp_token_account,
+            &amm_info.lp_mint_pubkey,
+        )?;
 
-        let (pda, bump_seed) = Pubkey::find_program_address(&[b"bravv"], program_id);
+        let bump_seed = amm_info.bump_seed;
+        let pda = Self::pool_authority(program_id, amm_account.key, bump_seed)?;
+        validation::check_pool_authority(pda_account, &pda)?;
 
-        let amm_account = next_account_info(account_info_iter)?;
-        let amm_info = AMM::unpack(&amm_account.data.borrow())?;
-        if amm_info.busd_token_account_pubkey != *pda_busd_token_account.key || amm_info.bstock_token_account_pubkey != *pda_bstock_token_account.key || amm_info.bstocklqdy_token_account_pubkey != *pda_bstocklqdy_token_account.key {
-            return Err(ProgramError::InvalidAccountData);
+        let now = Clock::get()?.unix_timestamp;
+        if !amm_info.is_withdrawable(now) {
+            return Err(AMMError::SaleNotEnded.into());
         }
-        let token_program = next_account_info(account_info_iter)?;
-        let transfer_bstocklqdy_to_amm = spl_token::instruction::transfer(
+        amm_info.update_price_accumulators(now);
+        Self::record_observation(program_id, amm_account, &amm_info, observations_account)?;
+        let (reserve_a, reserve_b) = (amm_info.reserve_a, amm_info.reserve_b);
+        let lp_mint_info = validation::unpack_mint(lp_mint)?;
+
+        if lp_amount > lp_mint_info.supply {
+            return Err(AMMError::InsufficientLiquidity.into());
+        }
+        let (amount_a, amount_b) =
+            curve::withdraw_amounts(lp_amount, reserve_a, reserve_b, lp_mint_info.supply)
+                .ok_or(AMMError::AmountOverflow)?;
+
+        let reserve_a = reserve_a
+            .checked_sub(amount_a)
+            .ok_or(AMMError::AmountOverflow)?;
+        let reserve_b = reserve_b
+            .checked_sub(amount_b)
+            .ok_or(AMMError::AmountOverflow)?;
+        if amm_info.curve_type == CurveType::ProactiveMarketMaker {
+            amm_info
+                .scale_targets(reserve_a, reserve_b)
+                .ok_or(AMMError::AmountOverflow)?;
+        }
+        amm_info.reserve_a = reserve_a;
+        amm_info.reserve_b = reserve_b;
+        AMM::pack(amm_info, &mut amm_account.data.borrow_mut())?;
+
+        let burn_lp = spl_token::instruction::burn(
             token_program.key,
-            liquidity_provider_bstocklqdy_token_account.key,
-            pda_bstocklqdy_token_account.key,
+            liquidity_provider_lp_token_account.key,
+            lp_mint.key,
             liquidity_provider_account.key,
-            &[&liquidity_provider_account.key],
-            bstocklqdy_amount,
+            &[liquidity_provider_account.key],
+            lp_amount,
         )?;
-        msg!("Calling the token program to transfer tokens to the AMM...");
+        msg!("Calling the token program to burn the liquidity provider's LP tokens...");
         invoke(
-            &transfer_bstocklqdy_to_amm,
+            &burn_lp,
             &[
-                liquidity_provider_bstocklqdy_token_account.clone(),
-                pda_busd_token_account.clone(),
+                liquidity_provider_lp_token_account.clone(),
+                lp_mint.clone(),
                 liquidity_provider_account.clone(),
                 token_program.clone(),
             ],
         )?;
 
-        let pda_account = next_account_info(account_info_iter)?;
-
-        let transfer_busd_to_liquidity_provider = spl_token::instruction::transfer(
+        let transfer_token_a_to_liquidity_provider = spl_token::instruction::transfer(
             token_program.key,
-            pda_bstocklqdy_token_account.key,
-            liquidity_provider_busd_token_account.key,
+            pda_token_a_account.key,
+            liquidity_provider_token_a_account.key,
             &pda,
             &[&pda],
-            busd_amount,
+            amount_a,
         )?;
-        let transfer_bstock_to_liquidity_provider = spl_token::instruction::transfer(
+        let transfer_token_b_to_liquidity_provider = spl_token::instruction::transfer(
             token_program.key,
-            pda_bstock_token_account.key,
-            liquidity_provider_bstock_token_account.key,
+            pda_token_b_account.key,
+            liquidity_provider_token_b_account.key,
             &pda,
             &[&pda],
-            bstock_amount,
+            amount_b,
         )?;
-        msg!("Calling the token program to transfer bStockLQDY to the liquidity provider...");
+        msg!("Calling the token program to transfer tokens to the liquidity provider...");
         invoke_signed(
-            &transfer_busd_to_liquidity_provider,
+            &transfer_token_a_to_liquidity_provider,
             &[
-                pda_busd_token_account.clone(),
-                liquidity_provider_busd_token_account.clone(),
+                pda_token_a_account.clone(),
+                liquidity_provider_token_a_account.clone(),
                 pda_account.clone(),
                 token_program.clone(),
             ],
-            &[&[&b"bravv"[..], &[bump_seed]]],
+            &[&[&b"bravv"[..], amm_account.key.as_ref(), &[bump_seed]]],
         )?;
         invoke_signed(
-            &transfer_bstock_to_liquidity_provider,
+            &transfer_token_b_to_liquidity_provider,
             &[
-                pda_bstock_token_account.clone(),
-                liquidity_provider_bstock_token_account.clone(),
+                pda_token_b_account.clone(),
+                liquidity_provider_token_b_account.clone(),
                 pda_account.clone(),
                 token_program.clone(),
             ],
-            &[&[&b"bravv"[..], &[bump_seed]]],
+            &[&[&b"bravv"[..], amm_account.key.as_ref(), &[bump_seed]]],
         )?;
 
         Ok(())
     }
 
-    fn process_trade_busd(
+    fn process_swap(
         accounts: &[AccountInfo],
-        busd_amount: u64,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        Self::process_trade(
+            accounts,
+            program_id,
+            Trade::ExactIn {
+                amount_in,
+                minimum_amount_out,
+            },
+        )
+    }
+
+    /// Prices a trade of `amount_in` against the (input, output) pool reserves and returns the amount out
+    /// along with the fee taken from `amount_in`. Shared by `Swap` and `Quote`.
+    fn quote_swap(
+        amm_info: &AMM,
+        a_to_b: bool,
+        amount_in: u64,
+        reserve_in: u64,
+        reserve_out: u64,
+    ) -> Result<(u64, u64), ProgramError> {
+        let amount_in_after_fee = curve::amount_without_fee(
+            amount_in,
+            amm_info.total_fee_numerator(),
+            amm_info.trade_fee_denominator,
+        )
+        .ok_or(AMMError::AmountOverflow)?;
+        let amount_out = amm_info
+            .swap_output(amount_in_after_fee, reserve_in, reserve_out, a_to_b)
+            .ok_or(AMMError::AmountOverflow)?;
+        if amount_out == 0 {
+            return Err(AMMError::InsufficientLiquidity.into());
+        }
+        Ok((amount_out, amount_in - amount_in_after_fee))
+    }
+
+    fn process_quote(
+        accounts: &[AccountInfo],
+        amount_in: u64,
+        a_to_b: bool,
         program_id: &Pubkey,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-        let user_account = next_account_info(account_info_iter)?;
-        if !user_account.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
+        let amm_account = next_account_info(account_info_iter)?;
+        let mut amm_info = validation::unpack_amm(amm_account, program_id)?;
+        let ticks_account = if amm_info.curve_type == CurveType::Concentrated {
+            Some(next_account_info(account_info_iter)?)
+        } else {
+            None
+        };
+        let oracle_account = if amm_info.oracle_pubkey != Pubkey::default() {
+            Some(next_account_info(account_info_iter)?)
+        } else {
+            None
+        };
+        let now = Clock::get()?.unix_timestamp;
+        if !amm_info.is_trading(now) {
+            return Err(AMMError::SaleNotActive.into());
+        }
+        amm_info.update_amplification(now);
+        amm_info.update_weights(now);
+        if amm_info.curve_type == CurveType::ProactiveMarketMaker {
+            let price = Self::oracle_price(program_id, &amm_info, oracle_account, now)?;
+            amm_info
+                .update_oracle_price(price)
+                .ok_or(AMMError::AmountOverflow)?;
+        } else if amm_info.oracle_pubkey != Pubkey::default() {
+            amm_info.oracle_price = Self::oracle_price(program_id, &amm_info, oracle_account, now)?;
+        }
+
+        let (reserve_in, reserve_out, amount_out, fee) =
+            if amm_info.curve_type == CurveType::Concentrated {
+                let mut ticks = Self::unpack_ticks(program_id, amm_account, ticks_account)?;
+                // The price impact is measured against a constant-product pool trading at the same price
+                let (reserve_a, reserve_b) = concentrated::virtual_reserves(amm_info.sqrt_price)
+                    .ok_or(AMMError::AmountOverflow)?;
+                let trade = Trade::ExactIn {
+                    amount_in,
+                    minimum_amount_out: 0,
+                };
+                let (_amount_in, amount_out, fee, _protocol_fee) =
+                    Self::swap_concentrated(&mut amm_info, &mut ticks, a_to_b, &trade)?;
+                if a_to_b {
+                    (reserve_a, reserve_b, amount_out, fee)
+                } else {
+                    (reserve_b, reserve_a, amount_out, fee)
+                }
+            } else {
+                let (reserve_in, reserve_out) = if a_to_b {
+                    (amm_info.reserve_a, amm_info.reserve_b)
+                } else {
+                    (amm_info.reserve_b, amm_info.reserve_a)
+                };
+                if reserve_in == 0 || reserve_out == 0 {
+                    return Err(AMMError::InsufficientLiquidity.into());
+                }
+                let (amount_out, fee) =
+                    Self::quote_swap(&amm_info, a_to_b, amount_in, reserve_in, reserve_out)?;
+                let protocol_fee = curve::fee_amount(
+                    amount_in,
+                    amm_info.protocol_fee_numerator,
+                    amm_info.trade_fee_denominator,
+                )
+                .ok_or(AMMError::AmountOverflow)?;
+                let reserve_in_after = reserve_in
+                    .checked_add(amount_in)
+                    .and_then(|reserve| reserve.checked_sub(protocol_fee))
+                    .ok_or(AMMError::AmountOverflow)?;
+                let reserve_out_after = reserve_out
+                    .checked_sub(amount_out)
+                    .ok_or(AMMError::AmountOverflow)?;
+                let (reserve_in, reserve_out) =
+                    if amm_info.curve_type == CurveType::ProactiveMarketMaker {
+                        // The price impact is measured against the pool's marginal price, not its reserves
+                        let (price_a, _price_b) = pmm::spot_prices(
+                            amm_info.reserve_a,
+                            amm_info.reserve_b,
+                            amm_info.target_a,
+                            amm_info.target_b,
+                            amm_info.oracle_price,
+                            amm_info.slippage_factor,
+                        );
+                        let (reserve_a, reserve_b) = price_a
+                            .and_then(pmm::virtual_reserves)
+                            .ok_or(AMMError::AmountOverflow)?;
+                        if a_to_b {
+                            (reserve_a, reserve_b)
+                        } else {
+                            (reserve_b, reserve_a)
+                        }
+                    } else {
+                        (reserve_in, reserve_out)
+                    };
+                if a_to_b {
+                    amm_info.reserve_a = reserve_in_after;
+                    amm_info.reserve_b = reserve_out_after;
+                } else {
+                    amm_info.reserve_b = reserve_in_after;
+                    amm_info.reserve_a = reserve_out_after;
+                }
+                (reserve_in, reserve_out, amount_out, fee)
+            };
+        // Like Swap, the trade must leave a guarded pool's price close enough to its oracle price
+        if !amm_info.is_within_price_deviation() {
+            return Err(AMMError::PriceDeviationExceeded.into());
+        }
+        let (weight_in, weight_out) = amm_info.weights(a_to_b);
+        let price_impact_bps = curve::price_impact_bps(
+            amount_in - fee,
+            amount_out,
+            reserve_in,
+            reserve_out,
+            weight_in,
+            weight_out,
+        )
+        .ok_or(AMMError::AmountOverflow)?;
+
+        let mut return_data = [0u8; 24];
+        return_data[..8].copy_from_slice(&amount_out.to_le_bytes());
+        return_data[8..16].copy_from_slice(&fee.to_le_bytes());
+        return_data[16..].copy_from_slice(&price_impact_bps.to_le_bytes());
+        set_return_data(&return_data);
+
+        Ok(())
+    }
+
+    fn process_swap_exact_out(
+        accounts: &[AccountInfo],
+        amount_out: u64,
+        maximum_amount_in: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        Self::process_trade(
+            accounts,
+            program_id,
+            Trade::ExactOut {
+                amount_out,
+                maximum_amount_in,
+            },
+        )
+    }
+
+    /// Prices `trade` against the pool's curve given whether token A is sold and the (input, output) pool
+    /// reserves, and returns the (input, output) amounts
+    fn quote_trade(
+        amm_info: &AMM,
+        a_to_b: bool,
+        trade: &Trade,
+        reserve_in: u64,
+        reserve_out: u64,
+    ) -> Result<(u64, u64), ProgramError> {
+        match *trade {
+            Trade::ExactIn { amount_in, .. } => {
+                let (amount_out, _fee) =
+                    Self::quote_swap(amm_info, a_to_b, amount_in, reserve_in, reserve_out)?;
+                Ok((amount_in, amount_out))
+            }
+            Trade::ExactOut { amount_out, .. } => {
+                if amount_out == 0 || amount_out >= reserve_out {
+                    return Err(AMMError::InsufficientLiquidity.into());
+                }
+                let amount_in_after_fee = amm_info
+                    .swap_input(amount_out, reserve_in, reserve_out, a_to_b)
+                    .ok_or(AMMError::AmountOverflow)?;
+                let amount_in = curve::amount_with_fee(
+                    amount_in_after_fee,
+                    amm_info.total_fee_numerator(),
+                    amm_info.trade_fee_denominator,
+                )
+                .ok_or(AMMError::AmountOverflow)?;
+                Ok((amount_in, amount_out))
+            }
         }
-        let user_busd_token_account = next_account_info(account_info_iter)?;
-        let user_bstock_token_account = next_account_info(account_info_iter)?;
+    }
 
-        let pda_busd_token_account = next_account_info(account_info_iter)?;
-        let pda_bstock_token_account = next_account_info(account_info_iter)?;
+    /// Trades against a concentrated-liquidity pool one range of constant liquidity at a time, crossing the
+    /// initialized ticks on the way and crediting each range's LP fees to the liquidity in it. Moves the
+    /// pool's price and returns the (input, output) amounts along with the total and protocol fees taken
+    /// from the input.
+    fn swap_concentrated(
+        amm_info: &mut AMM,
+        ticks: &mut Ticks,
+        a_to_b: bool,
+        trade: &Trade,
+    ) -> Result<(u64, u64, u64, u64), ProgramError> {
+        let (exact_in, amount) = match *trade {
+            Trade::ExactIn { amount_in, .. } => (true, amount_in),
+            Trade::ExactOut { amount_out, .. } => (false, amount_out),
+        };
+        let sqrt_price_limit = if a_to_b {
+            concentrated::min_sqrt_price()
+        } else {
+            concentrated::max_sqrt_price()
+        };
+        let total_fee_numerator = amm_info.total_fee_numerator();
 
-        let pda_busd_token_account_info =
-            TokenAccount::unpack(&pda_busd_token_account.data.borrow())?;
-        let pda_bstock_token_account_info =
-            TokenAccount::unpack(&pda_bstock_token_account.data.borrow())?;
-        // let pda_bstocklqdy_token_account_info =
-        //     TokenAccount::unpack(&pda_bstocklqdy_token_account.data.borrow())?;
+        let mut amount_remaining = amount;
+        let (mut amount_in, mut amount_out, mut fee, mut protocol_fee) = (0u64, 0u64, 0u64, 0u64);
+        while amount_remaining > 0 && amm_info.sqrt_price != sqrt_price_limit {
+            let next_tick = ticks.next_initialized(amm_info.tick_current, a_to_b);
+            let sqrt_price_target = match next_tick {
+                Some(tick) => {
+                    concentrated::sqrt_price_at_tick(tick.index).ok_or(AMMError::AmountOverflow)?
+                }
+                None => sqrt_price_limit,
+            };
+            let step = concentrated::compute_swap_step(
+                amm_info.sqrt_price,
+                sqrt_price_target,
+                amm_info.liquidity,
+                amount_remaining,
+                total_fee_numerator,
+                amm_info.trade_fee_denominator,
+                exact_in,
+            )
+            .ok_or(AMMError::AmountOverflow)?;
 
-        let amm_ratio = pda_busd_token_account_info.amount / pda_bstock_token_account_info.amount;
-        let bstock_amount = busd_amount * amm_ratio;
+            let step_amount_in = step
+                .amount_in
+                .checked_add(step.fee)
+                .ok_or(AMMError::AmountOverflow)?;
+            amount_remaining = amount_remaining
+                .checked_sub(if exact_in {
+                    step_amount_in
+                } else {
+                    step.amount_out
+                })
+                .ok_or(AMMError::AmountOverflow)?;
+            amount_in = amount_in
+                .checked_add(step_amount_in)
+                .ok_or(AMMError::AmountOverflow)?;
+            amount_out = amount_out
+                .checked_add(step.amount_out)
+                .ok_or(AMMError::AmountOverflow)?;
+            fee = fee.checked_add(step.fee).ok_or(AMMError::AmountOverflow)?;
 
-        let (pda, bump_seed) = Pubkey::find_program_address(&[b"bravv"], program_id);
+            let step_protocol_fee = if total_fee_numerator == 0 {
+                0
+            } else {
+                (step.fee as u128 * amm_info.protocol_fee_numerator as u128
+                    / total_fee_numerator as u128) as u64
+            };
+            protocol_fee = protocol_fee
+                .checked_add(step_protocol_fee)
+                .ok_or(AMMError::AmountOverflow)?;
+            if amm_info.liquidity > 0 {
+                let fee_growth =
+                    (((step.fee - step_protocol_fee) as u128) << 64) / amm_info.liquidity as u128;
+                if a_to_b {
+                    amm_info.fee_growth_global_a =
+                        amm_info.fee_growth_global_a.wrapping_add(fee_growth);
+                } else {
+                    amm_info.fee_growth_global_b =
+                        amm_info.fee_growth_global_b.wrapping_add(fee_growth);
+                }
+            }
 
-        let amm_account = next_account_info(account_info_iter)?;
-        let amm_info = AMM::unpack(&amm_account.data.borrow())?;
-        if amm_info.busd_token_account_pubkey != *pda_busd_token_account.key || amm_info.bstock_token_account_pubkey != *pda_bstock_token_account.key {
-            return Err(ProgramError::InvalidAccountData);
+            amm_info.sqrt_price = step.sqrt_price;
+            match next_tick {
+                Some(tick) if step.sqrt_price == sqrt_price_target => {
+                    let tick = ticks
+                        .get_mut(tick.index)
+                        .ok_or(AMMError::InvalidTicksAccount)?;
+                    tick.fee_growth_outside_a = amm_info
+                        .fee_growth_global_a
+                        .wrapping_sub(tick.fee_growth_outside_a);
+                    tick.fee_growth_outside_b = amm_info
+                        .fee_growth_global_b
+                        .wrapping_sub(tick.fee_growth_outside_b);
+                    let liquidity_delta = if a_to_b {
+                        -tick.liquidity_net
+                    } else {
+                        tick.liquidity_net
+                    };
+                    amm_info.liquidity =
+                        Self::add_liquidity_delta(amm_info.liquidity, liquidity_delta)?;
+                    amm_info.tick_current = if a_to_b { tick.index - 1 } else { tick.index };
+                }
+                _ => {
+                    amm_info.tick_current = concentrated::tick_at_sqrt_price(step.sqrt_price)
+                        .ok_or(AMMError::AmountOverflow)?;
+                }
+            }
+        }
+        if amount_remaining > 0 || amount_out == 0 {
+            return Err(AMMError::InsufficientLiquidity.into());
         }
+
+        Ok((amount_in, amount_out, fee, protocol_fee))
+    }
+
+    /// Moves the input of `trade` from the user into the pool and its output back to the user.
+    ///
+    /// The direction is picked from the mint of the user's source token account.
+    fn process_trade(accounts: &[AccountInfo], program_id: &Pubkey, trade: Trade) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let user_account = next_account_info(account_info_iter)?;
+        validation::check_signer(user_account)?;
+        let user_source_token_account = next_account_info(account_info_iter)?;
+        let user_destination_token_account = next_account_info(account_info_iter)?;
+
+        let pda_token_a_account = next_account_info(account_info_iter)?;
+        let pda_token_b_account = next_account_info(account_info_iter)?;
+
+        let amm_account = next_account_info(account_info_iter)?;
         let token_program = next_account_info(account_info_iter)?;
-        let transfer_busd_to_amm = spl_token::instruction::transfer(
+        let pda_account = next_account_info(account_info_iter)?;
+        let observations_account = next_account_info(account_info_iter).ok();
+        let ticks_account = next_account_info(account_info_iter).ok();
+        let oracle_account = next_account_info(account_info_iter).ok();
+
+        let mut amm_info = validation::unpack_amm(amm_account, program_id)?;
+        validation::check_pool_token_accounts(&amm_info, pda_token_a_account, pda_token_b_account)?;
+        validation::check_token_program(token_program)?;
+        validation::check_distinct(&[
+            user_source_token_account,
+            user_destination_token_account,
+            pda_token_a_account,
+            pda_token_b_account,
+            amm_account,
+        ])?;
+
+        let user_source_token_account_info =
+            validation::unpack_token_account(user_source_token_account)?;
+        let a_to_b = if user_source_token_account_info.mint == amm_info.mint_a_pubkey {
+            true
+        } else if user_source_token_account_info.mint == amm_info.mint_b_pubkey {
+            false
+        } else {
+            return Err(AMMError::InvalidMint.into());
+        };
+        let (pda_in_token_account, pda_out_token_account, mint_out) = if a_to_b {
+            (
+                pda_token_a_account,
+                pda_token_b_account,
+                &amm_info.mint_b_pubkey,
+            )
+        } else {
+            (
+                pda_token_b_account,
+                pda_token_a_account,
+                &amm_info.mint_a_pubkey,
+            )
+        };
+        validation::unpack_token_account_with_mint(user_destination_token_account, mint_out)?;
+
+        let bump_seed = amm_info.bump_seed;
+        let pda = Self::pool_authority(program_id, amm_account.key, bump_seed)?;
+        validation::check_pool_authority(pda_account, &pda)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        if !amm_info.is_trading(now) {
+            return Err(AMMError::SaleNotActive.into());
+        }
+        amm_info.update_price_accumulators(now);
+        amm_info.update_amplification(now);
+        amm_info.update_weights(now);
+        if amm_info.curve_type == CurveType::ProactiveMarketMaker {
+            let price = Self::oracle_price(program_id, &amm_info, oracle_account, now)?;
+            amm_info
+                .update_oracle_price(price)
+                .ok_or(AMMError::AmountOverflow)?;
+        } else if amm_info.oracle_pubkey != Pubkey::default() {
+            amm_info.oracle_price = Self::oracle_price(program_id, &amm_info, oracle_account, now)?;
+        }
+        Self::record_observation(program_id, amm_account, &amm_info, observations_account)?;
+        let (reserve_a, reserve_b) = (amm_info.reserve_a, amm_info.reserve_b);
+        let (reserve_in, reserve_out) = if a_to_b {
+            (reserve_a, reserve_b)
+        } else {
+            (reserve_b, reserve_a)
+        };
+        let (amount_in, amount_out, protocol_fee, invariant_before) =
+            if amm_info.curve_type == CurveType::Concentrated {
+                let ticks_account = ticks_account.ok_or(AMMError::InvalidTicksAccount)?;
+                let mut ticks = Self::unpack_ticks(program_id, amm_account, Some(ticks_account))?;
+                let (amount_in, amount_out, _fee, protocol_fee) =
+                    Self::swap_concentrated(&mut amm_info, &mut ticks, a_to_b, &trade)?;
+                Ticks::pack(ticks, &mut ticks_account.data.borrow_mut())?;
+                (amount_in, amount_out, protocol_fee, None)
+            } else {
+                if reserve_a == 0 || reserve_b == 0 {
+                    return Err(AMMError::InsufficientLiquidity.into());
+                }
+                let invariant_before = if amm_info.curve_type == CurveType::ProactiveMarketMaker {
+                    None
+                } else {
+                    let invariant = amm_info
+                        .invariant(reserve_a, reserve_b)
+                        .ok_or(AMMError::AmountOverflow)?;
+                    Some(invariant)
+                };
+                let (amount_in, amount_out) =
+                    Self::quote_trade(&amm_info, a_to_b, &trade, reserve_in, reserve_out)?;
+                let protocol_fee = curve::fee_amount(
+                    amount_in,
+                    amm_info.protocol_fee_numerator,
+                    amm_info.trade_fee_denominator,
+                )
+                .ok_or(AMMError::AmountOverflow)?;
+                (amount_in, amount_out, protocol_fee, invariant_before)
+            };
+        match trade {
+            Trade::ExactIn {
+                minimum_amount_out, ..
+            } if amount_out < minimum_amount_out => {
+                return Err(AMMError::ExceededSlippage.into());
+            }
+            Trade::ExactOut {
+                maximum_amount_in, ..
+            } if amount_in > maximum_amount_in => {
+                return Err(AMMError::ExceededSlippage.into());
+            }
+            _ => {}
+        }
+
+        let reserve_in_after = reserve_in
+            .checked_add(amount_in)
+            .and_then(|reserve| reserve.checked_sub(protocol_fee))
+            .ok_or(AMMError::AmountOverflow)?;
+        let reserve_out_after = reserve_out
+            .checked_sub(amount_out)
+            .ok_or(AMMError::AmountOverflow)?;
+        if a_to_b {
+            amm_info.protocol_fees_a = amm_info
+                .protocol_fees_a
+                .checked_add(protocol_fee)
+                .ok_or(AMMError::AmountOverflow)?;
+            amm_info.reserve_a = reserve_in_after;
+            amm_info.reserve_b = reserve_out_after;
+        } else {
+            amm_info.protocol_fees_b = amm_info
+                .protocol_fees_b
+                .checked_add(protocol_fee)
+                .ok_or(AMMError::AmountOverflow)?;
+            amm_info.reserve_b = reserve_in_after;
+            amm_info.reserve_a = reserve_out_after;
+        }
+        if let Some(invariant_before) = invariant_before {
+            Self::check_invariant(&amm_info, invariant_before)?;
+        }
+        if !amm_info.is_within_price_deviation() {
+            return Err(AMMError::PriceDeviationExceeded.into());
+        }
+
+        let transfer_to_amm = spl_token::instruction::transfer(
             token_program.key,
-            user_busd_token_account.key,
-            pda_busd_token_account.key,
+            user_source_token_account.key,
+            pda_in_token_account.key,
             user_account.key,
-            &[&user_account.key],
-            busd_amount,
+            &[user_account.key],
+            amount_in,
         )?;
         msg!("Calling the token program to transfer tokens to the AMM...");
         invoke(
-            &transfer_busd_to_amm,
+            &transfer_to_amm,
             &[
-                user_busd_token_account.clone(),
-                pda_busd_token_account.clone(),
+                user_source_token_account.clone(),
+                pda_in_token_account.clone(),
                 user_account.clone(),
                 token_program.clone(),
             ],
         )?;
 
+        let transfer_to_user = spl_token::instruction::transfer(
+            token_program.key,
+            pda_out_token_account.key,
+            user_destination_token_account.key,
+            &pda,
+            &[&pda],
+            amount_out,
+        )?;
+        msg!("Calling the token program to transfer tokens to the user...");
+        invoke_signed(
+            &transfer_to_user,
+            &[
+                pda_out_token_account.clone(),
+                user_destination_token_account.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[&b"bravv"[..], amm_account.key.as_ref(), &[bump_seed]]],
+        )?;
+
+        AMM::pack(amm_info, &mut amm_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Reads the price of the oracle account the pool is anchored to or guarded by, failing unless
+    /// `oracle_account` is that account or if its price is more than `max_oracle_staleness` seconds old at `now`
+    fn oracle_price(
+        program_id: &Pubkey,
+        amm_info: &AMM,
+        oracle_account: Option<&AccountInfo>,
+        now: i64,
+    ) -> Result<u128, ProgramError> {
+        let oracle_account = oracle_account.ok_or(AMMError::InvalidOracleAccount)?;
+        if *oracle_account.key != amm_info.oracle_pubkey {
+            return Err(AMMError::InvalidOracleAccount.into());
+        }
+        let oracle = validation::unpack_oracle(oracle_account, program_id)?;
+        let age = u64::try_from(now.saturating_sub(oracle.timestamp)).unwrap_or(0);
+        if age > amm_info.max_oracle_staleness {
+            return Err(AMMError::StaleOraclePrice.into());
+        }
+        Ok(oracle.price)
+    }
+
+    /// Derives the PDA owning a pool's token accounts from the pool's key and stored bump seed
+    fn pool_authority(
+        program_id: &Pubkey,
+        amm_account_key: &Pubkey,
+        bump_seed: u8,
+    ) -> Result<Pubkey, ProgramError> {
+        Pubkey::create_program_address(
+            &[b"bravv", amm_account_key.as_ref(), &[bump_seed]],
+            program_id,
+        )
+        .map_err(|_| ProgramError::InvalidSeeds)
+    }
+
+    /// Reads the PDA's token A and token B balances, net of the protocol fees accrued in them.
+    /// Anything above the recorded reserves was sent to the pool outside of its instructions.
+    fn vault_balances(
+        amm_info: &AMM,
+        pda_token_a_account: &AccountInfo,
+        pda_token_b_account: &AccountInfo,
+    ) -> Result<(u64, u64), ProgramError> {
+        let pda_token_a_account_info = validation::unpack_token_account(pda_token_a_account)?;
+        let pda_token_b_account_info = validation::unpack_token_account(pda_token_b_account)?;
+
+        let reserve_a = pda_token_a_account_info
+            .amount
+            .checked_sub(amm_info.protocol_fees_a)
+            .ok_or(AMMError::AmountOverflow)?;
+        let reserve_b = pda_token_b_account_info
+            .amount
+            .checked_sub(amm_info.protocol_fees_b)
+            .ok_or(AMMError::AmountOverflow)?;
+
+        Ok((reserve_a, reserve_b))
+    }
+
+    /// Records the AMM's cumulative prices in the pool's observation account, if it was passed and has been
+    /// created, unless it already holds an observation from the current slot
+    fn record_observation(
+        program_id: &Pubkey,
+        amm_account: &AccountInfo,
+        amm_info: &AMM,
+        observations_account: Option<&AccountInfo>,
+    ) -> ProgramResult {
+        let observations_account = match observations_account {
+            Some(observations_account) => observations_account,
+            None => return Ok(()),
+        };
+        let (observations_address, _bump_seed) =
+            instruction::find_observations_address(program_id, amm_account.key);
+        if *observations_account.key != observations_address {
+            return Err(AMMError::InvalidObservationsAccount.into());
+        }
+        if observations_account.owner != program_id {
+            return Ok(());
+        }
+
+        let mut observations = Observations::unpack(&observations_account.data.borrow())?;
+        let observation = Observation {
+            timestamp: amm_info.last_update_timestamp,
+            price_a_cumulative: amm_info.price_a_cumulative,
+            price_b_cumulative: amm_info.price_b_cumulative,
+        };
+        if observations.record(Clock::get()?.slot, observation) {
+            Observations::pack(observations, &mut observations_account.data.borrow_mut())?;
+        }
+
+        Ok(())
+    }
+
+    /// Unpacks the ticks account of the concentrated-liquidity pool stored in `amm_account`, which must be passed
+    fn unpack_ticks(
+        program_id: &Pubkey,
+        amm_account: &AccountInfo,
+        ticks_account: Option<&AccountInfo>,
+    ) -> Result<Ticks, ProgramError> {
+        let ticks_account = ticks_account.ok_or(AMMError::InvalidTicksAccount)?;
+        let (ticks_address, _bump_seed) =
+            instruction::find_ticks_address(program_id, amm_account.key);
+        if *ticks_account.key != ticks_address || ticks_account.owner != program_id {
+            return Err(AMMError::InvalidTicksAccount.into());
+        }
+        Ticks::unpack(&ticks_account.data.borrow())
+    }
+
+    /// Adds the signed `liquidity_delta` to `liquidity`
+    fn add_liquidity_delta(liquidity: u64, liquidity_delta: i128) -> Result<u64, ProgramError> {
+        i128::from(liquidity)
+            .checked_add(liquidity_delta)
+            .and_then(|liquidity| u64::try_from(liquidity).ok())
+            .ok_or_else(|| AMMError::AmountOverflow.into())
+    }
+
+    /// Counts the fees `position` earned since it was last modified into its owed fees, then adds the signed
+    /// `liquidity_delta` to it, to its ticks, initializing or uninitializing them as needed, and to the
+    /// pool's active liquidity if its range contains the current price
+    fn modify_position(
+        amm_info: &mut AMM,
+        ticks: &mut Ticks,
+        position: &mut Position,
+        liquidity_delta: i128,
+    ) -> ProgramResult {
+        let (tick_lower, tick_upper) = (position.tick_lower, position.tick_upper);
+        if position.liquidity == 0 && liquidity_delta == 0 {
+            return Ok(());
+        }
+
+        for index in [tick_lower, tick_upper] {
+            if ticks.get(index).is_some() {
+                continue;
+            }
+            // By convention, all fees so far were earned below a tick initialized at or below the current price
+            let (fee_growth_outside_a, fee_growth_outside_b) = if index <= amm_info.tick_current {
+                (amm_info.fee_growth_global_a, amm_info.fee_growth_global_b)
+            } else {
+                (0, 0)
+            };
+            let tick = Tick {
+                index,
+                liquidity_gross: 0,
+                liquidity_net: 0,
+                fee_growth_outside_a,
+                fee_growth_outside_b,
+            };
+            if !ticks.insert(tick) {
+                return Err(AMMError::TooManyTicks.into());
+            }
+        }
+
+        let lower = *ticks.get(tick_lower).ok_or(AMMError::InvalidTicksAccount)?;
+        let upper = *ticks.get(tick_upper).ok_or(AMMError::InvalidTicksAccount)?;
+        let fee_growth_inside_a = concentrated::fee_growth_inside(
+            amm_info.tick_current,
+            tick_lower,
+            tick_upper,
+            amm_info.fee_growth_global_a,
+            lower.fee_growth_outside_a,
+            upper.fee_growth_outside_a,
+        );
+        let fee_growth_inside_b = concentrated::fee_growth_inside(
+            amm_info.tick_current,
+            tick_lower,
+            tick_upper,
+            amm_info.fee_growth_global_b,
+            lower.fee_growth_outside_b,
+            upper.fee_growth_outside_b,
+        );
+        let fees_a = concentrated::fees_earned(
+            fee_growth_inside_a.wrapping_sub(position.fee_growth_inside_a_last),
+            position.liquidity,
+        )
+        .ok_or(AMMError::AmountOverflow)?;
+        let fees_b = concentrated::fees_earned(
+            fee_growth_inside_b.wrapping_sub(position.fee_growth_inside_b_last),
+            position.liquidity,
+        )
+        .ok_or(AMMError::AmountOverflow)?;
+        position.fees_owed_a = position
+            .fees_owed_a
+            .checked_add(fees_a)
+            .ok_or(AMMError::AmountOverflow)?;
+        position.fees_owed_b = position
+            .fees_owed_b
+            .checked_add(fees_b)
+            .ok_or(AMMError::AmountOverflow)?;
+        position.fee_growth_inside_a_last = fee_growth_inside_a;
+        position.fee_growth_inside_b_last = fee_growth_inside_b;
+        position.liquidity = Self::add_liquidity_delta(position.liquidity, liquidity_delta)?;
+
+        for (index, liquidity_net_delta) in [
+            (tick_lower, liquidity_delta),
+            (tick_upper, -liquidity_delta),
+        ] {
+            let tick = ticks.get_mut(index).ok_or(AMMError::InvalidTicksAccount)?;
+            tick.liquidity_gross =
+                Self::add_liquidity_delta(tick.liquidity_gross, liquidity_delta)?;
+            tick.liquidity_net = tick
+                .liquidity_net
+                .checked_add(liquidity_net_delta)
+                .ok_or(AMMError::AmountOverflow)?;
+            if tick.liquidity_gross == 0 {
+                ticks.remove(index);
+            }
+        }
+        if (tick_lower..tick_upper).contains(&amm_info.tick_current) {
+            amm_info.liquidity = Self::add_liquidity_delta(amm_info.liquidity, liquidity_delta)?;
+        }
+
+        Ok(())
+    }
+
+    /// Fails if the pool's invariant went down over a trade, given the AMM info with the updated reserves
+    fn check_invariant(amm_info: &AMM, invariant_before: u128) -> ProgramResult {
+        let invariant_after = amm_info
+            .invariant(amm_info.reserve_a, amm_info.reserve_b)
+            .ok_or(AMMError::AmountOverflow)?;
+        if invariant_after.saturating_add(amm_info.invariant_precision()) < invariant_before {
+            return Err(AMMError::InvariantViolated.into());
+        }
+
+        Ok(())
+    }
+
+    fn process_withdraw_protocol_fees(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let fee_authority_account = next_account_info(account_info_iter)?;
+        validation::check_signer(fee_authority_account)?;
+        let destination_token_a_account = next_account_info(account_info_iter)?;
+        let destination_token_b_account = next_account_info(account_info_iter)?;
+
+        let pda_token_a_account = next_account_info(account_info_iter)?;
+        let pda_token_b_account = next_account_info(account_info_iter)?;
+
+        let amm_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
         let pda_account = next_account_info(account_info_iter)?;
 
-        let transfer_bstock_to_user = spl_token::instruction::transfer(
+        let mut amm_info = validation::unpack_amm(amm_account, program_id)?;
+        if amm_info.fee_authority_pubkey != *fee_authority_account.key {
+            return Err(AMMError::InvalidFeeAuthority.into());
+        }
+        validation::check_pool_token_accounts(&amm_info, pda_token_a_account, pda_token_b_account)?;
+        validation::check_token_program(token_program)?;
+        validation::check_distinct(&[
+            destination_token_a_account,
+            destination_token_b_account,
+            pda_token_a_account,
+            pda_token_b_account,
+            amm_account,
+        ])?;
+        validation::unpack_token_account_with_mint(
+            destination_token_a_account,
+            &amm_info.mint_a_pubkey,
+        )?;
+        validation::unpack_token_account_with_mint(
+            destination_token_b_account,
+            &amm_info.mint_b_pubkey,
+        )?;
+
+        let bump_seed = amm_info.bump_seed;
+        let pda = Self::pool_authority(program_id, amm_account.key, bump_seed)?;
+        validation::check_pool_authority(pda_account, &pda)?;
+
+        let amount_a = amm_info.protocol_fees_a;
+        let amount_b = amm_info.protocol_fees_b;
+        amm_info.protocol_fees_a = 0;
+        amm_info.protocol_fees_b = 0;
+        AMM::pack(amm_info, &mut amm_account.data.borrow_mut())?;
+
+        let transfer_token_a_to_destination = spl_token::instruction::transfer(
             token_program.key,
-            pda_bstock_token_account.key,
-            user_bstock_token_account.key,
+            pda_token_a_account.key,
+            destination_token_a_account.key,
             &pda,
             &[&pda],
-            bstock_amount,
+            amount_a,
         )?;
-        msg!("Calling the token program to transfer bStock to the user...");
+        let transfer_token_b_to_destination = spl_token::instruction::transfer(
+            token_program.key,
+            pda_token_b_account.key,
+            destination_token_b_account.key,
+            &pda,
+            &[&pda],
+            amount_b,
+        )?;
+        msg!("Calling the token program to transfer protocol fees...");
         invoke_signed(
-            &transfer_bstock_to_user,
+            &transfer_token_a_to_destination,
             &[
-                pda_bstock_token_account.clone(),
-                user_bstock_token_account.clone(),
+                pda_token_a_account.clone(),
+                destination_token_a_account.clone(),
                 pda_account.clone(),
                 token_program.clone(),
             ],
-            &[&[&b"bravv"[..], &[bump_seed]]],
+            &[&[&b"bravv"[..], amm_account.key.as_ref(), &[bump_seed]]],
         )?;
+        invoke_signed(
+            &transfer_token_b_to_destination,
+            &[
+                pda_token_b_account.clone(),
+                destination_token_b_account.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[&b"bravv"[..], amm_account.key.as_ref(), &[bump_seed]]],
+        )?;
+
+        Ok(())
+    }
+
+    fn process_sync(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pda_token_a_account = next_account_info(account_info_iter)?;
+        let pda_token_b_account = next_account_info(account_info_iter)?;
+        let amm_account = next_account_info(account_info_iter)?;
+        let observations_account = next_account_info(account_info_iter).ok();
+
+        let mut amm_info = validation::unpack_amm(amm_account, program_id)?;
+        if amm_info.curve_type == CurveType::Concentrated {
+            return Err(AMMError::InvalidCurveType.into());
+        }
+        validation::check_pool_token_accounts(&amm_info, pda_token_a_account, pda_token_b_account)?;
+
+        amm_info.update_price_accumulators(Clock::get()?.unix_timestamp);
+        Self::record_observation(program_id, amm_account, &amm_info, observations_account)?;
+        let (reserve_a, reserve_b) =
+            Self::vault_balances(&amm_info, pda_token_a_account, pda_token_b_account)?;
+        amm_info.reserve_a = reserve_a;
+        amm_info.reserve_b = reserve_b;
+        AMM::pack(amm_info, &mut amm_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_skim(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let destination_token_a_account = next_account_info(account_info_iter)?;
+        let destination_token_b_account = next_account_info(account_info_iter)?;
+
+        let pda_token_a_account = next_account_info(account_info_iter)?;
+        let pda_token_b_account = next_account_info(account_info_iter)?;
+
+        let amm_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let pda_account = next_account_info(account_info_iter)?;
+
+        let amm_info = validation::unpack_amm(amm_account, program_id)?;
+        if amm_info.curve_type == CurveType::Concentrated {
+            return Err(AMMError::InvalidCurveType.into());
+        }
+        validation::check_pool_token_accounts(&amm_info, pda_token_a_account, pda_token_b_account)?;
+        validation::check_token_program(token_program)?;
+        validation::check_distinct(&[
+            destination_token_a_account,
+            destination_token_b_account,
+            pda_token_a_account,
+            pda_token_b_account,
+            amm_account,
+        ])?;
+        validation::unpack_token_account_with_mint(
+            destination_token_a_account,
+            &amm_info.mint_a_pubkey,
+        )?;
+        validation::unpack_token_account_with_mint(
+            destination_token_b_account,
+            &amm_info.mint_b_pubkey,
+        )?;
+
+        let bump_seed = amm_info.bump_seed;
+        let pda = Self::pool_authority(program_id, amm_account.key, bump_seed)?;
+        validation::check_pool_authority(pda_account, &pda)?;
+
+        let (balance_a, balance_b) =
+            Self::vault_balances(&amm_info, pda_token_a_account, pda_token_b_account)?;
+        let excess_a = balance_a.saturating_sub(amm_info.reserve_a);
+        let excess_b = balance_b.saturating_sub(amm_info.reserve_b);
+
+        let transfer_token_a_to_destination = spl_token::instruction::transfer(
+            token_program.key,
+            pda_token_a_account.key,
+            destination_token_a_account.key,
+            &pda,
+            &[&pda],
+            excess_a,
+        )?;
+        let transfer_token_b_to_destination = spl_token::instruction::transfer(
+            token_program.key,
+            pda_token_b_account.key,
+            destination_token_b_account.key,
+            &pda,
+            &[&pda],
+            excess_b,
+        )?;
+        msg!("Calling the token program to transfer the excess tokens...");
+        invoke_signed(
+            &transfer_token_a_to_destination,
+            &[
+                pda_token_a_account.clone(),
+                destination_token_a_account.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[&b"bravv"[..], amm_account.key.as_ref(), &[bump_seed]]],
+        )?;
+        invoke_signed(
+            &transfer_token_b_to_destination,
+            &[
+                pda_token_b_account.clone(),
+                destination_token_b_account.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[&b"bravv"[..], amm_account.key.as_ref(), &[bump_seed]]],
+        )?;
+
+        Ok(())
+    }
+
+    fn process_init_observations(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let payer_account = next_account_info(account_info_iter)?;
+        validation::check_signer(payer_account)?;
+        let observations_account = next_account_info(account_info_iter)?;
+        let amm_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        validation::check_system_program(system_program)?;
+
+        let amm_info = validation::unpack_amm(amm_account, program_id)?;
+        let (observations_address, bump_seed) =
+            instruction::find_observations_address(program_id, amm_account.key);
+        if *observations_account.key != observations_address {
+            return Err(AMMError::InvalidObservationsAccount.into());
+        }
+
+        let create_observations_ix = system_instruction::create_account(
+            payer_account.key,
+            observations_account.key,
+            Rent::get()?.minimum_balance(Observations::LEN),
+            Observations::LEN as u64,
+            program_id,
+        );
+        msg!("Calling the system program to create the observation account...");
+        invoke_signed(
+            &create_observations_ix,
+            &[
+                payer_account.clone(),
+                observations_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                &b"bravv"[..],
+                amm_account.key.as_ref(),
+                &b"observations"[..],
+                &[bump_seed],
+            ]],
+        )?;
+
+        let mut observations = Observations::unpack_unchecked(&observations_account.data.borrow())?;
+        observations.is_initialized = true;
+        observations.amm_pubkey = *amm_account.key;
+        observations.record(
+            Clock::get()?.slot,
+            Observation {
+                timestamp: amm_info.last_update_timestamp,
+                price_a_cumulative: amm_info.price_a_cumulative,
+                price_b_cumulative: amm_info.price_b_cumulative,
+            },
+        );
+        Observations::pack(observations, &mut observations_account.data.borrow_mut())?;
 
         Ok(())
     }
 
-    fn process_trade_bstock(
+    fn process_query_twap(
         accounts: &[AccountInfo],
-        busd_amount: u64,
+        window_seconds: u64,
         program_id: &Pubkey,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-        let user_account = next_account_info(account_info_iter)?;
-        if !user_account.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
+        let amm_account = next_account_info(account_info_iter)?;
+        let observations_account = next_account_info(account_info_iter)?;
+
+        let mut amm_info = validation::unpack_amm(amm_account, program_id)?;
+        let (observations_address, _bump_seed) =
+            instruction::find_observations_address(program_id, amm_account.key);
+        if *observations_account.key != observations_address
+            || observations_account.owner != program_id
+        {
+            return Err(AMMError::InvalidObservationsAccount.into());
+        }
+        let observations = Observations::unpack(&observations_account.data.borrow())?;
+
+        let now = Clock::get()?.unix_timestamp;
+        amm_info.update_price_accumulators(now);
+        let window_start = i64::try_from(window_seconds)
+            .ok()
+            .and_then(|window_seconds| now.checked_sub(window_seconds))
+            .ok_or(AMMError::AmountOverflow)?;
+        if window_seconds == 0 {
+            return Err(AMMError::InsufficientObservations.into());
         }
-        let user_busd_token_account = next_account_info(account_info_iter)?;
-        let user_bstock_token_account = next_account_info(account_info_iter)?;
+        let current = Observation {
+            timestamp: now,
+            price_a_cumulative: amm_info.price_a_cumulative,
+            price_b_cumulative: amm_info.price_b_cumulative,
+        };
+        let (price_a_cumulative_start, price_b_cumulative_start) = observations
+            .cumulative_prices_at(window_start, &current)
+            .ok_or(AMMError::InsufficientObservations)?;
+
+        let price_a_average = amm_info
+            .price_a_cumulative
+            .wrapping_sub(price_a_cumulative_start)
+            / window_seconds as u128;
+        let price_b_average = amm_info
+            .price_b_cumulative
+            .wrapping_sub(price_b_cumulative_start)
+            / window_seconds as u128;
+        let mut return_data = [0u8; 32];
+        return_data[..16].copy_from_slice(&price_a_average.to_le_bytes());
+        return_data[16..].copy_from_slice(&price_b_average.to_le_bytes());
+        set_return_data(&return_data);
+
+        Ok(())
+    }
+
+    fn process_ramp_amplification(
+        accounts: &[AccountInfo],
+        target_amplification: u64,
+        end_timestamp: i64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let initializer_account = next_account_info(account_info_iter)?;
+        validation::check_signer(initializer_account)?;
+        let amm_account = next_account_info(account_info_iter)?;
 
-        let pda_busd_token_account = next_account_info(account_info_iter)?;
-        let pda_bstock_token_account = next_account_info(account_info_iter)?;
+        let mut amm_info = validation::unpack_amm(amm_account, program_id)?;
+        if amm_info.initializer_account_pubkey != *initializer_account.key {
+            return Err(AMMError::InvalidInitializer.into());
+        }
+        if amm_info.curve_type != CurveType::StableSwap
+            || !(curve::MIN_AMPLIFICATION..=curve::MAX_AMPLIFICATION)
+                .contains(&target_amplification)
+        {
+            return Err(AMMError::InvalidAmplification.into());
+        }
 
-        let pda_busd_token_account_info =
-            TokenAccount::unpack(&pda_busd_token_account.data.borrow())?;
-        let pda_bstock_token_account_info =
-            TokenAccount::unpack(&pda_bstock_token_account.data.borrow())?;
-        // let pda_bstocklqdy_token_account_info =
-        //     TokenAccount::unpack(&pda_bstocklqdy_token_account.data.borrow())?;
+        let now = Clock::get()?.unix_timestamp;
+        if end_timestamp < now.saturating_add(curve::MIN_RAMP_DURATION) {
+            return Err(AMMError::InvalidRamp.into());
+        }
+        amm_info.update_amplification(now);
+        let amplification = amm_info.amplification;
+        if target_amplification > amplification.saturating_mul(curve::MAX_AMPLIFICATION_CHANGE)
+            || amplification > target_amplification.saturating_mul(curve::MAX_AMPLIFICATION_CHANGE)
+        {
+            return Err(AMMError::InvalidAmplification.into());
+        }
 
-        let amm_ratio = pda_busd_token_account_info.amount / pda_bstock_token_account_info.amount;
-        let bstock_amount = busd_amount * amm_ratio;
+        amm_info.ramp_start_amplification = amplification;
+        amm_info.target_amplification = target_amplification;
+        amm_info.ramp_start_timestamp = now;
+        amm_info.ramp_end_timestamp = end_timestamp;
+        AMM::pack(amm_info, &mut amm_account.data.borrow_mut())?;
 
-        let (pda, bump_seed) = Pubkey::find_program_address(&[b"bravv"], program_id);
+        Ok(())
+    }
 
+    fn process_stop_ramp(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let initializer_account = next_account_info(account_info_iter)?;
+        validation::check_signer(initializer_account)?;
         let amm_account = next_account_info(account_info_iter)?;
-        let amm_info = AMM::unpack(&amm_account.data.borrow())?;
-        if amm_info.busd_token_account_pubkey != *pda_busd_token_account.key || amm_info.bstock_token_account_pubkey != *pda_bstock_token_account.key {
-            return Err(ProgramError::InvalidAccountData);
+
+        let mut amm_info = validation::unpack_amm(amm_account, program_id)?;
+        if amm_info.initializer_account_pubkey != *initializer_account.key {
+            return Err(AMMError::InvalidInitializer.into());
         }
+        if amm_info.curve_type != CurveType::StableSwap {
+            return Err(AMMError::InvalidAmplification.into());
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        amm_info.update_amplification(now);
+        amm_info.ramp_start_amplification = amm_info.amplification;
+        amm_info.target_amplification = amm_info.amplification;
+        amm_info.ramp_start_timestamp = now;
+        amm_info.ramp_end_timestamp = now;
+        AMM::pack(amm_info, &mut amm_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_init_ticks(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let payer_account = next_account_info(account_info_iter)?;
+        validation::check_signer(payer_account)?;
+        let ticks_account = next_account_info(account_info_iter)?;
+        let amm_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        validation::check_system_program(system_program)?;
+
+        let amm_info = validation::unpack_amm(amm_account, program_id)?;
+        if amm_info.curve_type != CurveType::Concentrated {
+            return Err(AMMError::InvalidCurveType.into());
+        }
+        let (ticks_address, bump_seed) =
+            instruction::find_ticks_address(program_id, amm_account.key);
+        if *ticks_account.key != ticks_address {
+            return Err(AMMError::InvalidTicksAccount.into());
+        }
+
+        let create_ticks_ix = system_instruction::create_account(
+            payer_account.key,
+            ticks_account.key,
+            Rent::get()?.minimum_balance(Ticks::LEN),
+            Ticks::LEN as u64,
+            program_id,
+        );
+        msg!("Calling the system program to create the ticks account...");
+        invoke_signed(
+            &create_ticks_ix,
+            &[
+                payer_account.clone(),
+                ticks_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                &b"bravv"[..],
+                amm_account.key.as_ref(),
+                &b"ticks"[..],
+                &[bump_seed],
+            ]],
+        )?;
+
+        let mut ticks = Ticks::unpack_unchecked(&ticks_account.data.borrow())?;
+        ticks.is_initialized = true;
+        ticks.amm_pubkey = *amm_account.key;
+        Ticks::pack(ticks, &mut ticks_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn process_increase_liquidity(
+        accounts: &[AccountInfo],
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u64,
+        maximum_amount_a: u64,
+        maximum_amount_b: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_account = next_account_info(account_info_iter)?;
+        validation::check_signer(owner_account)?;
+        let owner_token_a_account = next_account_info(account_info_iter)?;
+        let owner_token_b_account = next_account_info(account_info_iter)?;
+
+        let pda_token_a_account = next_account_info(account_info_iter)?;
+        let pda_token_b_account = next_account_info(account_info_iter)?;
+
+        let amm_account = next_account_info(account_info_iter)?;
+        let ticks_account = next_account_info(account_info_iter)?;
+        let position_account = next_account_info(account_info_iter)?;
         let token_program = next_account_info(account_info_iter)?;
-        let transfer_bstock_to_amm = spl_token::instruction::transfer(
+        let system_program = next_account_info(account_info_iter)?;
+
+        let mut amm_info = validation::unpack_amm(amm_account, program_id)?;
+        if amm_info.curve_type != CurveType::Concentrated {
+            return Err(AMMError::InvalidCurveType.into());
+        }
+        validation::check_pool_token_accounts(&amm_info, pda_token_a_account, pda_token_b_account)?;
+        validation::check_token_program(token_program)?;
+        validation::check_system_program(system_program)?;
+        validation::check_distinct(&[
+            owner_token_a_account,
+            owner_token_b_account,
+            pda_token_a_account,
+            pda_token_b_account,
+            amm_account,
+            ticks_account,
+            position_account,
+        ])?;
+        validation::unpack_token_account_with_mint(owner_token_a_account, &amm_info.mint_a_pubkey)?;
+        validation::unpack_token_account_with_mint(owner_token_b_account, &amm_info.mint_b_pubkey)?;
+
+        if !concentrated::is_valid_tick_range(tick_lower, tick_upper, amm_info.tick_spacing) {
+            return Err(AMMError::InvalidTickRange.into());
+        }
+        if liquidity == 0 {
+            return Err(AMMError::InsufficientLiquidity.into());
+        }
+        let mut ticks = Self::unpack_ticks(program_id, amm_account, Some(ticks_account))?;
+
+        let (position_address, bump_seed) = instruction::find_position_address(
+            program_id,
+            amm_account.key,
+            owner_account.key,
+            tick_lower,
+            tick_upper,
+        );
+        if *position_account.key != position_address {
+            return Err(AMMError::InvalidPositionAccount.into());
+        }
+        if position_account.owner != program_id {
+            let create_position_ix = system_instruction::create_account(
+                owner_account.key,
+                position_account.key,
+                Rent::get()?.minimum_balance(Position::LEN),
+                Position::LEN as u64,
+                program_id,
+            );
+            msg!("Calling the system program to create the position account...");
+            invoke_signed(
+                &create_position_ix,
+                &[
+                    owner_account.clone(),
+                    position_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[
+                    &b"bravv"[..],
+                    amm_account.key.as_ref(),
+                    &b"position"[..],
+                    owner_account.key.as_ref(),
+                    &tick_lower.to_le_bytes(),
+                    &tick_upper.to_le_bytes(),
+                    &[bump_seed],
+                ]],
+            )?;
+
+            let mut position = Position::unpack_unchecked(&position_account.data.borrow())?;
+            position.is_initialized = true;
+            position.amm_pubkey = *amm_account.key;
+            position.owner_pubkey = *owner_account.key;
+            position.tick_lower = tick_lower;
+            position.tick_upper = tick_upper;
+            Position::pack(position, &mut position_account.data.borrow_mut())?;
+        }
+        let mut position = Position::unpack(&position_account.data.borrow())?;
+
+        Self::modify_position(&mut amm_info, &mut ticks, &mut position, liquidity as i128)?;
+        let (amount_a, amount_b) = concentrated::amounts_for_liquidity(
+            amm_info.sqrt_price,
+            amm_info.tick_current,
+            tick_lower,
+            tick_upper,
+            liquidity,
+            true,
+        )
+        .ok_or(AMMError::AmountOverflow)?;
+        if amount_a > maximum_amount_a || amount_b > maximum_amount_b {
+            return Err(AMMError::ExceededSlippage.into());
+        }
+
+        amm_info.reserve_a = amm_info
+            .reserve_a
+            .checked_add(amount_a)
+            .ok_or(AMMError::AmountOverflow)?;
+        amm_info.reserve_b = amm_info
+            .reserve_b
+            .checked_add(amount_b)
+            .ok_or(AMMError::AmountOverflow)?;
+        AMM::pack(amm_info, &mut amm_account.data.borrow_mut())?;
+        Ticks::pack(ticks, &mut ticks_account.data.borrow_mut())?;
+        Position::pack(position, &mut position_account.data.borrow_mut())?;
+
+        let transfer_token_a_to_amm = spl_token::instruction::transfer(
             token_program.key,
-            user_bstock_token_account.key,
-            pda_bstock_token_account.key,
-            user_account.key,
-            &[&user_account.key],
-            bstock_amount,
+            owner_token_a_account.key,
+            pda_token_a_account.key,
+            owner_account.key,
+            &[owner_account.key],
+            amount_a,
+        )?;
+        let transfer_token_b_to_amm = spl_token::instruction::transfer(
+            token_program.key,
+            owner_token_b_account.key,
+            pda_token_b_account.key,
+            owner_account.key,
+            &[owner_account.key],
+            amount_b,
         )?;
         msg!("Calling the token program to transfer tokens to the AMM...");
         invoke(
-            &transfer_bstock_to_amm,
+            &transfer_token_a_to_amm,
             &[
-                user_bstock_token_account.clone(),
-                pda_bstock_token_account.clone(),
-                user_account.clone(),
+                owner_token_a_account.clone(),
+                pda_token_a_account.clone(),
+                owner_account.clone(),
                 token_program.clone(),
             ],
         )?;
+        invoke(
+            &transfer_token_b_to_amm,
+            &[
+                owner_token_b_account.clone(),
+                pda_token_b_account.clone(),
+                owner_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn process_decrease_liquidity(
+        accounts: &[AccountInfo],
+        liquidity: u64,
+        minimum_amount_a: u64,
+        minimum_amount_b: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        Self::process_position_withdrawal(accounts, program_id, |amm_info, ticks, position| {
+            if liquidity == 0 || liquidity > position.liquidity {
+                return Err(AMMError::InsufficientLiquidity.into());
+            }
+            Self::modify_position(amm_info, ticks, position, -(liquidity as i128))?;
+            let (amount_a, amount_b) = concentrated::amounts_for_liquidity(
+                amm_info.sqrt_price,
+                amm_info.tick_current,
+                position.tick_lower,
+                position.tick_upper,
+                liquidity,
+                false,
+            )
+            .ok_or(AMMError::AmountOverflow)?;
+            if amount_a < minimum_amount_a || amount_b < minimum_amount_b {
+                return Err(AMMError::ExceededSlippage.into());
+            }
+            Ok((amount_a, amount_b))
+        })
+    }
 
+    fn process_collect_fees(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        Self::process_position_withdrawal(accounts, program_id, |amm_info, ticks, position| {
+            Self::modify_position(amm_info, ticks, position, 0)?;
+            let amounts = (position.fees_owed_a, position.fees_owed_b);
+            position.fees_owed_a = 0;
+            position.fees_owed_b = 0;
+            Ok(amounts)
+        })
+    }
+
+    /// Pays amounts out of a position to its owner.
+    ///
+    /// `withdraw` receives the AMM info, the pool's ticks and the position, updates them and returns the token A
+    /// and token B amounts to pay out.
+    fn process_position_withdrawal<F>(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        withdraw: F,
+    ) -> ProgramResult
+    where
+        F: FnOnce(&mut AMM, &mut Ticks, &mut Position) -> Result<(u64, u64), ProgramError>,
+    {
+        let account_info_iter = &mut accounts.iter();
+        let owner_account = next_account_info(account_info_iter)?;
+        validation::check_signer(owner_account)?;
+        let owner_token_a_account = next_account_info(account_info_iter)?;
+        let owner_token_b_account = next_account_info(account_info_iter)?;
+
+        let pda_token_a_account = next_account_info(account_info_iter)?;
+        let pda_token_b_account = next_account_info(account_info_iter)?;
+
+        let amm_account = next_account_info(account_info_iter)?;
+        let ticks_account = next_account_info(account_info_iter)?;
+        let position_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
         let pda_account = next_account_info(account_info_iter)?;
 
-        let transfer_busd_to_user = spl_token::instruction::transfer(
+        let mut amm_info = validation::unpack_amm(amm_account, program_id)?;
+        if amm_info.curve_type != CurveType::Concentrated {
+            return Err(AMMError::InvalidCurveType.into());
+        }
+        validation::check_pool_token_accounts(&amm_info, pda_token_a_account, pda_token_b_account)?;
+        validation::check_token_program(token_program)?;
+        validation::check_distinct(&[
+            owner_token_a_account,
+            owner_token_b_account,
+            pda_token_a_account,
+            pda_token_b_account,
+            amm_account,
+            ticks_account,
+            position_account,
+        ])?;
+        validation::unpack_token_account_with_mint(owner_token_a_account, &amm_info.mint_a_pubkey)?;
+        validation::unpack_token_account_with_mint(owner_token_b_account, &amm_info.mint_b_pubkey)?;
+
+        let bump_seed = amm_info.bump_seed;
+        let pda = Self::pool_authority(program_id, amm_account.key, bump_seed)?;
+        validation::check_pool_authority(pda_account, &pda)?;
+
+        let mut ticks = Self::unpack_ticks(program_id, amm_account, Some(ticks_account))?;
+        if position_account.owner != program_id {
+            return Err(AMMError::InvalidPositionAccount.into());
+        }
+        let mut position = Position::unpack(&position_account.data.borrow())?;
+        if position.amm_pubkey != *amm_account.key || position.owner_pubkey != *owner_account.key {
+            return Err(AMMError::InvalidPositionAccount.into());
+        }
+
+        let (amount_a, amount_b) = withdraw(&mut amm_info, &mut ticks, &mut position)?;
+        amm_info.reserve_a = amm_info
+            .reserve_a
+            .checked_sub(amount_a)
+            .ok_or(AMMError::AmountOverflow)?;
+        amm_info.reserve_b = amm_info
+            .reserve_b
+            .checked_sub(amount_b)
+            .ok_or(AMMError::AmountOverflow)?;
+        AMM::pack(amm_info, &mut amm_account.data.borrow_mut())?;
+        Ticks::pack(ticks, &mut ticks_account.data.borrow_mut())?;
+        Position::pack(position, &mut position_account.data.borrow_mut())?;
+
+        let transfer_token_a_to_owner = spl_token::instruction::transfer(
+            token_program.key,
+            pda_token_a_account.key,
+            owner_token_a_account.key,
+            &pda,
+            &[&pda],
+            amount_a,
+        )?;
+        let transfer_token_b_to_owner = spl_token::instruction::transfer(
             token_program.key,
-            pda_busd_token_account.key,
-            user_busd_token_account.key,
+            pda_token_b_account.key,
+            owner_token_b_account.key,
             &pda,
             &[&pda],
-            bstock_amount,
+            amount_b,
         )?;
-        msg!("Calling the token program to transfer bStock to the user...");
+        msg!("Calling the token program to transfer tokens to the position owner...");
         invoke_signed(
-            &transfer_busd_to_user,
+            &transfer_token_a_to_owner,
             &[
-                pda_bstock_token_account.clone(),
-                user_bstock_token_account.clone(),
+                pda_token_a_account.clone(),
+                owner_token_a_account.clone(),
                 pda_account.clone(),
                 token_program.clone(),
             ],
-            &[&[&b"bravv"[..], &[bump_seed]]],
+            &[&[&b"bravv"[..], amm_account.key.as_ref(), &[bump_seed]]],
+        )?;
+        invoke_signed(
+            &transfer_token_b_to_owner,
+            &[
+                pda_token_b_account.clone(),
+                owner_token_b_account.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[&b"bravv"[..], amm_account.key.as_ref(), &[bump_seed]]],
         )?;
 
         Ok(())
     }
 
-    fn process_close_amm(
+    fn process_init_oracle(
         accounts: &[AccountInfo],
+        price: u128,
         program_id: &Pubkey,
     ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority_account = next_account_info(account_info_iter)?;
+        validation::check_signer(authority_account)?;
+        let oracle_account = next_account_info(account_info_iter)?;
+        let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+
+        if oracle_account.owner != program_id {
+            return Err(AMMError::InvalidOracleAccount.into());
+        }
+        if !rent.is_exempt(oracle_account.lamports(), oracle_account.data_len()) {
+            return Err(AMMError::NotRentExempt.into());
+        }
+        let oracle = Oracle::unpack_unchecked(&oracle_account.data.borrow())?;
+        if oracle.is_initialized() {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        if price == 0 {
+            return Err(AMMError::InvalidRatio.into());
+        }
+
+        let oracle = Oracle {
+            is_initialized: true,
+            authority_pubkey: *authority_account.key,
+            price,
+            timestamp: Clock::get()?.unix_timestamp,
+        };
+        Oracle::pack(oracle, &mut oracle_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_update_oracle(
+        accounts: &[AccountInfo],
+        price: u128,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority_account = next_account_info(account_info_iter)?;
+        validation::check_signer(authority_account)?;
+        let oracle_account = next_account_info(account_info_iter)?;
+
+        let mut oracle = validation::unpack_oracle(oracle_account, program_id)?;
+        if oracle.authority_pubkey != *authority_account.key {
+            return Err(AMMError::InvalidOracleAuthority.into());
+        }
+        if price == 0 {
+            return Err(AMMError::InvalidRatio.into());
+        }
+
+        oracle.price = price;
+        oracle.timestamp = Clock::get()?.unix_timestamp;
+        Oracle::pack(oracle, &mut oracle_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_close_amm(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let initializer_account = next_account_info(account_info_iter)?;
-        let pdas_busd_token_account = next_account_info(account_info_iter)?;
-        let pdas_bstock_token_account = next_account_info(account_info_iter)?;
-        let pdas_bstocklqdy_token_account = next_account_info(account_info_iter)?;
-        
+        validation::check_signer(initializer_account)?;
+        let initializer_token_a_account = next_account_info(account_info_iter)?;
+        let initializer_token_b_account = next_account_info(account_info_iter)?;
+        let pdas_token_a_account = next_account_info(account_info_iter)?;
+        let pdas_token_b_account = next_account_info(account_info_iter)?;
+        let lp_mint = next_account_info(account_info_iter)?;
+
         let amm_account = next_account_info(account_info_iter)?;
-        let amm_info = AMM::unpack(&amm_account.data.borrow())?;
+        let token_program = next_account_info(account_info_iter)?;
+        let pda_account = next_account_info(account_info_iter)?;
+
+        let amm_info = validation::unpack_amm(amm_account, program_id)?;
         if amm_info.initializer_account_pubkey != *initializer_account.key {
             return Err(AMMError::InvalidInitializer.into());
         }
+        if amm_info.curve_type == CurveType::Concentrated {
+            return Err(AMMError::InvalidCurveType.into());
+        }
+        validation::check_pool_token_accounts(
+            &amm_info,
+            pdas_token_a_account,
+            pdas_token_b_account,
+        )?;
+        validation::check_pool_mint(&amm_info, lp_mint)?;
+        validation::check_token_program(token_program)?;
+        validation::check_distinct(&[
+            initializer_account,
+            initializer_token_a_account,
+            initializer_token_b_account,
+            pdas_token_a_account,
+            pdas_token_b_account,
+            lp_mint,
+            amm_account,
+        ])?;
+        validation::unpack_token_account_with_mint(
+            initializer_token_a_account,
+            &amm_info.mint_a_pubkey,
+        )?;
+        validation::unpack_token_account_with_mint(
+            initializer_token_b_account,
+            &amm_info.mint_b_pubkey,
+        )?;
 
-        let token_program = next_account_info(account_info_iter)?;
-        let pda_account = next_account_info(account_info_iter)?;
+        let bump_seed = amm_info.bump_seed;
+        let pda = Self::pool_authority(program_id, amm_account.key, bump_seed)?;
+        validation::check_pool_authority(pda_account, &pda)?;
 
-        let (pda, bump_seed) = Pubkey::find_program_address(&[b"bravv"], program_id);
+        let lp_mint_info = validation::unpack_mint(lp_mint)?;
+        if lp_mint_info.supply != 0
+            || amm_info.protocol_fees_a != 0
+            || amm_info.protocol_fees_b != 0
+        {
+            return Err(AMMError::PoolNotEmpty.into());
+        }
 
-        let close_pdas_busd_account = spl_token::instruction::close_account(
+        let (locked_amount_a, locked_amount_b) =
+            Self::vault_balances(&amm_info, pdas_token_a_account, pdas_token_b_account)?;
+        let transfer_token_a_to_initializer = spl_token::instruction::transfer(
             token_program.key,
-            pdas_busd_token_account.key,
-            initializer_account.key,
+            pdas_token_a_account.key,
+            initializer_token_a_account.key,
             &pda,
             &[&pda],
+            locked_amount_a,
         )?;
-        let close_pdas_bstock_account = spl_token::instruction::close_account(
+        let transfer_token_b_to_initializer = spl_token::instruction::transfer(
             token_program.key,
-            pdas_busd_token_account.key,
-            initializer_account.key,
+            pdas_token_b_account.key,
+            initializer_token_b_account.key,
             &pda,
             &[&pda],
+            locked_amount_b,
         )?;
-        let close_pdas_bstocklqdy_account = spl_token::instruction::close_account(
-            token_program.key,
-            pdas_busd_token_account.key,
-            initializer_account.key,
-            &pda,
-            &[&pda],
+        msg!("Calling the token program to transfer the locked liquidity to the initializer...");
+        invoke_signed(
+            &transfer_token_a_to_initializer,
+            &[
+                pdas_token_a_account.clone(),
+                initializer_token_a_account.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[&b"bravv"[..], amm_account.key.as_ref(), &[bump_seed]]],
         )?;
-        msg!("Calling the token program to close pda's accounts...");
         invoke_signed(
-            &close_pdas_busd_account,
+            &transfer_token_b_to_initializer,
             &[
-                pdas_busd_token_account.clone(),
-                initializer_account.clone(),
+                pdas_token_b_account.clone(),
+                initializer_token_b_account.clone(),
                 pda_account.clone(),
                 token_program.clone(),
             ],
-            &[&[&b"escrow"[..], &[bump_seed]]],
+            &[&[&b"bravv"[..], amm_account.key.as_ref(), &[bump_seed]]],
+        )?;
+
+        let close_pdas_token_a_account = spl_token::instruction::close_account(
+            token_program.key,
+            pdas_token_a_account.key,
+            initializer_account.key,
+            &pda,
+            &[&pda],
         )?;
+        let close_pdas_token_b_account = spl_token::instruction::close_account(
+            token_program.key,
+            pdas_token_b_account.key,
+            initializer_account.key,
+            &pda,
+            &[&pda],
+        )?;
+        msg!("Calling the token program to close pda's accounts...");
         invoke_signed(
-            &close_pdas_bstock_account,
+            &close_pdas_token_a_account,
             &[
-                pdas_bstock_token_account.clone(),
+                pdas_token_a_account.clone(),
                 initializer_account.clone(),
                 pda_account.clone(),
                 token_program.clone(),
             ],
-            &[&[&b"escrow"[..], &[bump_seed]]],
+            &[&[&b"bravv"[..], amm_account.key.as_ref(), &[bump_seed]]],
         )?;
         invoke_signed(
-            &close_pdas_bstocklqdy_account,
+            &close_pdas_token_b_account,
             &[
-                pdas_bstocklqdy_token_account.clone(),
+                pdas_token_b_account.clone(),
                 initializer_account.clone(),
                 pda_account.clone(),
                 token_program.clone(),
             ],
-            &[&[&b"escrow"[..], &[bump_seed]]],
+            &[&[&b"bravv"[..], amm_account.key.as_ref(), &[bump_seed]]],
         )?;
 
-        msg!("Closing the escrow account...");
+        msg!("Closing the AMM account...");
         **initializer_account.lamports.borrow_mut() = initializer_account
             .lamports()
-            .checked_add(pda_account.lamports())
+            .checked_add(amm_account.lamports())
             .ok_or(AMMError::AmountOverflow)?;
-        **pda_account.lamports.borrow_mut() = 0;
-        *pda_account.data.borrow_mut() = &mut [];
+        **amm_account.lamports.borrow_mut() = 0;
+        amm_account.data.borrow_mut().fill(0);
 
         Ok(())
     }
-
 }
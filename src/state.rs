@@ -1,18 +1,410 @@
 // inside state.rs
 use solana_program::{
-    program_pack::{IsInitialized, Pack, Sealed},
     program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
     pubkey::Pubkey,
 };
 
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 
+use std::convert::TryFrom;
+
+use crate::{
+    concentrated,
+    curve::{self, CurveType},
+    pmm,
+};
+
 pub struct AMM {
     pub is_initialized: bool,
     pub initializer_account_pubkey: Pubkey,
-    pub busd_token_account_pubkey: Pubkey,
-    pub bstock_token_account_pubkey: Pubkey,
-    pub bstocklqdy_token_account_pubkey: Pubkey,
+    pub token_a_account_pubkey: Pubkey,
+    pub token_b_account_pubkey: Pubkey,
+    pub lp_mint_pubkey: Pubkey,
+    pub mint_a_pubkey: Pubkey,
+    pub mint_b_pubkey: Pubkey,
+    pub trade_fee_numerator: u64,
+    pub trade_fee_denominator: u64,
+    pub protocol_fee_numerator: u64,
+    pub fee_authority_pubkey: Pubkey,
+    pub protocol_fees_a: u64,
+    pub protocol_fees_b: u64,
+    pub bump_seed: u8,
+    pub reserve_a: u64,
+    pub reserve_b: u64,
+    pub price_a_cumulative: u128,
+    pub price_b_cumulative: u128,
+    pub last_update_timestamp: i64,
+    pub curve_type: CurveType,
+    /// Amplification coefficient of a StableSwap pool as of the last trade, unused by other curves
+    pub amplification: u64,
+    /// The amplification coefficient is ramped linearly from `ramp_start_amplification` at
+    /// `ramp_start_timestamp` to `target_amplification` at `ramp_end_timestamp`, see
+    /// [amplification_at](#method.amplification_at)
+    pub ramp_start_amplification: u64,
+    pub target_amplification: u64,
+    pub ramp_start_timestamp: i64,
+    pub ramp_end_timestamp: i64,
+    /// Normalized weights of token A and token B as of the last trade, summing to
+    /// [WEIGHT_ONE](../curve/constant.WEIGHT_ONE.html). Only weighted pools weigh them unevenly.
+    pub weight_a: u64,
+    pub weight_b: u64,
+    /// The weight of token A moves linearly from `start_weight_a` at `weights_start_timestamp` to
+    /// `end_weight_a` at `weights_end_timestamp`, see [weight_a_at](#method.weight_a_at). Only
+    /// liquidity bootstrapping pools move it, and only trade between those timestamps.
+    pub start_weight_a: u64,
+    pub end_weight_a: u64,
+    pub weights_start_timestamp: i64,
+    pub weights_end_timestamp: i64,
+    /// Distance between the ticks that the positions of a concentrated-liquidity pool may start or end at,
+    /// 0 for other curves
+    pub tick_spacing: u16,
+    /// Square root of the price of token A in token B of a concentrated-liquidity pool as a UQ64.64
+    /// fixed-point number, and the tick it falls in
+    pub sqrt_price: u128,
+    pub tick_current: i32,
+    /// Liquidity of the positions whose range contains `tick_current`
+    pub liquidity: u64,
+    /// LP fees earned per unit of liquidity over the pool's lifetime, in token A and token B, as UQ64.64
+    /// fixed-point numbers meant to wrap around
+    pub fee_growth_global_a: u128,
+    pub fee_growth_global_b: u128,
+    /// Account publishing the reference price of a proactive market maker pool or of a pool guarded against
+    /// deviating from it, see [Oracle](struct.Oracle.html), and how many seconds old its price may be for the
+    /// pool to trade. `Pubkey::default()` if the pool has no oracle.
+    pub oracle_pubkey: Pubkey,
+    pub max_oracle_staleness: u64,
+    /// Price of token A in token B read from the oracle as of the last trade, as a UQ64.64 fixed-point number
+    pub oracle_price: u128,
+    /// Slippage factor `k` of a proactive market maker pool, over
+    /// [SLIPPAGE_FACTOR_ONE](../pmm/constant.SLIPPAGE_FACTOR_ONE.html)
+    pub slippage_factor: u64,
+    /// Reserves at which a proactive market maker pool trades at the oracle price, see
+    /// [update_oracle_price](#method.update_oracle_price)
+    pub target_a: u64,
+    pub target_b: u64,
+    /// Largest deviation of the pool's price after a trade from the oracle price, in basis points of the
+    /// oracle price, 0 if trades are not guarded
+    pub max_price_deviation_bps: u64,
+}
+
+/// Linearly interpolates between `start_value` at `start_timestamp` and `end_value` at `end_timestamp`,
+/// holding either value outside of that range
+fn interpolate(
+    start_value: u64,
+    end_value: u64,
+    start_timestamp: i64,
+    end_timestamp: i64,
+    now: i64,
+) -> u64 {
+    if now >= end_timestamp {
+        return end_value;
+    }
+    if now <= start_timestamp {
+        return start_value;
+    }
+    let elapsed = (now - start_timestamp) as u128;
+    let duration = (end_timestamp - start_timestamp) as u128;
+    // Never overflows: both values fit in a u64 and elapsed < duration
+    if end_value >= start_value {
+        start_value + ((end_value - start_value) as u128 * elapsed / duration) as u64
+    } else {
+        start_value - ((start_value - end_value) as u128 * elapsed / duration) as u64
+    }
+}
+
+impl AMM {
+    /// The LP and protocol fees charged on a trade's input, over `trade_fee_denominator`
+    pub fn total_fee_numerator(&self) -> u64 {
+        self.trade_fee_numerator + self.protocol_fee_numerator
+    }
+
+    /// Interpolates the amplification coefficient at `now` along the current ramp. Once the ramp is
+    /// over, or when there is none, it is `target_amplification`.
+    pub fn amplification_at(&self, now: i64) -> u64 {
+        interpolate(
+            self.ramp_start_amplification,
+            self.target_amplification,
+            self.ramp_start_timestamp,
+            self.ramp_end_timestamp,
+            now,
+        )
+    }
+
+    /// Sets `amplification` to its value at `now`. Must be called before pricing a trade.
+    pub fn update_amplification(&mut self, now: i64) {
+        self.amplification = self.amplification_at(now);
+    }
+
+    /// Interpolates the weight of token A at `now` along the pool's weight schedule. Before it starts it is
+    /// `start_weight_a` and once it is over `end_weight_a`.
+    pub fn weight_a_at(&self, now: i64) -> u64 {
+        interpolate(
+            self.start_weight_a,
+            self.end_weight_a,
+            self.weights_start_timestamp,
+            self.weights_end_timestamp,
+            now,
+        )
+    }
+
+    /// Sets `weight_a` and `weight_b` to their values at `now`. Must be called before pricing a trade.
+    pub fn update_weights(&mut self, now: i64) {
+        self.weight_a = self.weight_a_at(now);
+        self.weight_b = curve::WEIGHT_ONE - self.weight_a;
+    }
+
+    /// Whether the pool trades at `now`: a liquidity bootstrapping pool only does during its sale,
+    /// from `weights_start_timestamp` until `weights_end_timestamp`
+    pub fn is_trading(&self, now: i64) -> bool {
+        self.curve_type != CurveType::LiquidityBootstrapping
+            || (self.weights_start_timestamp..self.weights_end_timestamp).contains(&now)
+    }
+
+    /// Whether liquidity can be withdrawn at `now`: a liquidity bootstrapping pool locks it until its sale ends
+    pub fn is_withdrawable(&self, now: i64) -> bool {
+        self.curve_type != CurveType::LiquidityBootstrapping || now >= self.weights_end_timestamp
+    }
+
+    /// The (input, output) token weights of a trade selling token A if `a_to_b` and token B otherwise
+    pub fn weights(&self, a_to_b: bool) -> (u64, u64) {
+        if a_to_b {
+            (self.weight_a, self.weight_b)
+        } else {
+            (self.weight_b, self.weight_a)
+        }
+    }
+
+    /// Sets `oracle_price` to `price` and re-derives the target of the token the pool is short of at it.
+    /// Must be called before pricing a trade against a proactive market maker pool.
+    pub fn update_oracle_price(&mut self, price: u128) -> Option<()> {
+        let (target_a, target_b) = pmm::targets(
+            self.reserve_a,
+            self.reserve_b,
+            self.target_a,
+            self.target_b,
+            price,
+            self.slippage_factor,
+        )?;
+        self.oracle_price = price;
+        self.target_a = target_a;
+        self.target_b = target_b;
+        Some(())
+    }
+
+    /// The (input, output) targets of a trade selling token A if `a_to_b` and token B otherwise
+    pub fn targets(&self, a_to_b: bool) -> (u64, u64) {
+        if a_to_b {
+            (self.target_a, self.target_b)
+        } else {
+            (self.target_b, self.target_a)
+        }
+    }
+
+    /// Scales the targets along with the reserves when a pro-rata deposit or withdrawal moves them to
+    /// `reserve_a` and `reserve_b`, which leaves the prices of a proactive market maker pool unchanged.
+    /// Must be called before the reserves change.
+    pub fn scale_targets(&mut self, reserve_a: u64, reserve_b: u64) -> Option<()> {
+        let scale = |target: u64, reserve_after: u64, reserve: u64| {
+            let target = (target as u128 * reserve_after as u128).checked_div(reserve as u128)?;
+            u64::try_from(target).ok()
+        };
+        self.target_a = scale(self.target_a, reserve_a, self.reserve_a)?;
+        self.target_b = scale(self.target_b, reserve_b, self.reserve_b)?;
+        Some(())
+    }
+
+    /// Computes the LP tokens issued for seeding the empty pool with `amount_a` and `amount_b`:
+    /// the invariant of those reserves, square-rooted for the constant product so it grows linearly too,
+    /// minus [MINIMUM_LIQUIDITY](../curve/constant.MINIMUM_LIQUIDITY.html).
+    ///
+    /// This and the other pricing methods below return `None` for concentrated-liquidity pools, which have
+    /// no LP tokens and price trades one tick range at a time instead.
+    pub fn initial_liquidity(&self, amount_a: u64, amount_b: u64) -> Option<u64> {
+        match self.curve_type {
+            CurveType::ConstantProduct | CurveType::ProactiveMarketMaker => {
+                curve::initial_liquidity(amount_a, amount_b)
+            }
+            CurveType::StableSwap => {
+                let liquidity = curve::stable_invariant(self.amplification, amount_a, amount_b)?;
+                let liquidity = u64::try_from(liquidity).ok()?;
+                Some(liquidity.saturating_sub(curve::MINIMUM_LIQUIDITY))
+            }
+            CurveType::Weighted | CurveType::LiquidityBootstrapping => {
+                curve::weighted_initial_liquidity(self.weight_a, self.weight_b, amount_a, amount_b)
+            }
+            CurveType::Concentrated => None,
+        }
+    }
+
+    /// Computes the output of a trade of `amount_in` against the pool's curve, selling token A if `a_to_b`
+    pub fn swap_output(
+        &self,
+        amount_in: u64,
+        reserve_in: u64,
+        reserve_out: u64,
+        a_to_b: bool,
+    ) -> Option<u64> {
+        match self.curve_type {
+            CurveType::ConstantProduct => curve::swap_output(amount_in, reserve_in, reserve_out),
+            CurveType::StableSwap => {
+                curve::stable_swap_output(self.amplification, amount_in, reserve_in, reserve_out)
+            }
+            CurveType::Weighted | CurveType::LiquidityBootstrapping => {
+                let (weight_in, weight_out) = self.weights(a_to_b);
+                curve::weighted_swap_output(
+                    weight_in,
+                    weight_out,
+                    amount_in,
+                    reserve_in,
+                    reserve_out,
+                )
+            }
+            CurveType::Concentrated => None,
+            CurveType::ProactiveMarketMaker => {
+                let (target_in, target_out) = self.targets(a_to_b);
+                pmm::swap_output(
+                    amount_in,
+                    reserve_in,
+                    reserve_out,
+                    target_in,
+                    target_out,
+                    self.oracle_price,
+                    self.slippage_factor,
+                    a_to_b,
+                )
+            }
+        }
+    }
+
+    /// Computes the input needed to take exactly `amount_out` out of the pool's curve, selling token A if `a_to_b`
+    pub fn swap_input(
+        &self,
+        amount_out: u64,
+        reserve_in: u64,
+        reserve_out: u64,
+        a_to_b: bool,
+    ) -> Option<u64> {
+        match self.curve_type {
+            CurveType::ConstantProduct => curve::swap_input(amount_out, reserve_in, reserve_out),
+            CurveType::StableSwap => {
+                curve::stable_swap_input(self.amplification, amount_out, reserve_in, reserve_out)
+            }
+            CurveType::Weighted | CurveType::LiquidityBootstrapping => {
+                let (weight_in, weight_out) = self.weights(a_to_b);
+                curve::weighted_swap_input(
+                    weight_in,
+                    weight_out,
+                    amount_out,
+                    reserve_in,
+                    reserve_out,
+                )
+            }
+            CurveType::Concentrated => None,
+            CurveType::ProactiveMarketMaker => {
+                let (target_in, target_out) = self.targets(a_to_b);
+                pmm::swap_input(
+                    amount_out,
+                    reserve_in,
+                    reserve_out,
+                    target_in,
+                    target_out,
+                    self.oracle_price,
+                    self.slippage_factor,
+                    a_to_b,
+                )
+            }
+        }
+    }
+
+    /// Computes the pool's invariant for the given reserves, which trades must never decrease
+    /// by more than [invariant_precision](#method.invariant_precision).
+    ///
+    /// Proactive market maker pools have none either: selling back the token they are short of pays out
+    /// their earlier premium, and the oracle price moves their curve.
+    pub fn invariant(&self, reserve_a: u64, reserve_b: u64) -> Option<u128> {
+        match self.curve_type {
+            CurveType::ConstantProduct => Some(curve::invariant(reserve_a, reserve_b)),
+            CurveType::StableSwap => {
+                curve::stable_invariant(self.amplification, reserve_a, reserve_b)
+            }
+            CurveType::Weighted | CurveType::LiquidityBootstrapping => {
+                curve::weighted_invariant(self.weight_a, self.weight_b, reserve_a, reserve_b)
+            }
+            CurveType::Concentrated | CurveType::ProactiveMarketMaker => None,
+        }
+    }
+
+    /// How far off the computed [invariant](#method.invariant) may be
+    pub fn invariant_precision(&self) -> u128 {
+        match self.curve_type {
+            CurveType::ConstantProduct => 0,
+            CurveType::StableSwap => 1,
+            CurveType::Weighted | CurveType::LiquidityBootstrapping => {
+                curve::WEIGHTED_INVARIANT_PRECISION
+            }
+            CurveType::Concentrated | CurveType::ProactiveMarketMaker => 0,
+        }
+    }
+
+    /// Computes the prices of token A in token B and of token B in token A implied by the pool's state, as
+    /// UQ64.64 fixed-point numbers
+    pub fn spot_prices(&self) -> (Option<u128>, Option<u128>) {
+        match self.curve_type {
+            CurveType::Concentrated => concentrated::spot_prices(self.sqrt_price),
+            CurveType::ProactiveMarketMaker => pmm::spot_prices(
+                self.reserve_a,
+                self.reserve_b,
+                self.target_a,
+                self.target_b,
+                self.oracle_price,
+                self.slippage_factor,
+            ),
+            _ => (
+                curve::spot_price(self.reserve_a, self.weight_a, self.reserve_b, self.weight_b),
+                curve::spot_price(self.reserve_b, self.weight_b, self.reserve_a, self.weight_a),
+            ),
+        }
+    }
+
+    /// Whether the price of token A in token B implied by the pool's state is within `max_price_deviation_bps`
+    /// of the oracle price. Always true for pools that are not guarded.
+    pub fn is_within_price_deviation(&self) -> bool {
+        if self.max_price_deviation_bps == 0 {
+            return true;
+        }
+        let price = match self.spot_prices() {
+            (Some(price), _) => price,
+            _ => return false,
+        };
+        let deviation = price.max(self.oracle_price) - price.min(self.oracle_price);
+        match curve::mul_div(deviation, 10_000, self.oracle_price, true) {
+            Some(deviation_bps) => deviation_bps <= self.max_price_deviation_bps as u128,
+            None => false,
+        }
+    }
+
+    /// Adds the prices implied by the current reserves, weighted by the seconds elapsed since
+    /// `last_update_timestamp`, to the cumulative prices. Must be called before the reserves change.
+    ///
+    /// `price_a_cumulative` sums the price of token A in token B and `price_b_cumulative` the price of
+    /// token B in token A, both as UQ64.64 fixed-point numbers. They are meant to overflow: consumers
+    /// take the wrapping difference of two observations and divide it by the time between them.
+    pub fn update_price_accumulators(&mut self, now: i64) {
+        let elapsed = now.saturating_sub(self.last_update_timestamp);
+        if elapsed <= 0 {
+            return;
+        }
+        if let (Some(price_a), Some(price_b)) = self.spot_prices() {
+            self.price_a_cumulative = self
+                .price_a_cumulative
+                .wrapping_add(price_a.wrapping_mul(elapsed as u128));
+            self.price_b_cumulative = self
+                .price_b_cumulative
+                .wrapping_add(price_b.wrapping_mul(elapsed as u128));
+        }
+        self.last_update_timestamp = now;
+    }
 }
 
 impl Sealed for AMM {}
@@ -24,16 +416,58 @@ impl IsInitialized for AMM {
 }
 
 impl Pack for AMM {
-    const LEN: usize = 129;
+    const LEN: usize = 561;
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
         let src = array_ref![src, 0, AMM::LEN];
         let (
             is_initialized,
             initializer_account_pubkey,
-            busd_token_account_pubkey,
-            bstock_token_account_pubkey,
-            bstocklqdy_token_account_pubkey,
-        ) = array_refs![src, 1, 32, 32, 32, 32];
+            token_a_account_pubkey,
+            token_b_account_pubkey,
+            lp_mint_pubkey,
+            mint_a_pubkey,
+            mint_b_pubkey,
+            trade_fee_numerator,
+            trade_fee_denominator,
+            protocol_fee_numerator,
+            fee_authority_pubkey,
+            protocol_fees_a,
+            protocol_fees_b,
+            bump_seed,
+            reserve_a,
+            reserve_b,
+            price_a_cumulative,
+            price_b_cumulative,
+            last_update_timestamp,
+            curve_type,
+            amplification,
+            ramp_start_amplification,
+            target_amplification,
+            ramp_start_timestamp,
+            ramp_end_timestamp,
+            weight_a,
+            weight_b,
+            start_weight_a,
+            end_weight_a,
+            weights_start_timestamp,
+            weights_end_timestamp,
+            tick_spacing,
+            sqrt_price,
+            tick_current,
+            liquidity,
+            fee_growth_global_a,
+            fee_growth_global_b,
+            oracle_pubkey,
+            max_oracle_staleness,
+            oracle_price,
+            slippage_factor,
+            target_a,
+            target_b,
+            max_price_deviation_bps,
+        ) = array_refs![
+            src, 1, 32, 32, 32, 32, 32, 32, 8, 8, 8, 32, 8, 8, 1, 8, 8, 16, 16, 8, 1, 8, 8, 8, 8,
+            8, 8, 8, 8, 8, 8, 8, 2, 16, 4, 8, 16, 16, 32, 8, 16, 8, 8, 8, 8
+        ];
         let is_initialized = match is_initialized {
             [0] => false,
             [1] => true,
@@ -41,11 +475,50 @@ impl Pack for AMM {
         };
 
         Ok(AMM {
-            is_initialized, 
+            is_initialized,
             initializer_account_pubkey: Pubkey::new_from_array(*initializer_account_pubkey),
-            busd_token_account_pubkey: Pubkey::new_from_array(*busd_token_account_pubkey),
-            bstock_token_account_pubkey: Pubkey::new_from_array(*bstock_token_account_pubkey),
-            bstocklqdy_token_account_pubkey: Pubkey::new_from_array(*bstocklqdy_token_account_pubkey),
+            token_a_account_pubkey: Pubkey::new_from_array(*token_a_account_pubkey),
+            token_b_account_pubkey: Pubkey::new_from_array(*token_b_account_pubkey),
+            lp_mint_pubkey: Pubkey::new_from_array(*lp_mint_pubkey),
+            mint_a_pubkey: Pubkey::new_from_array(*mint_a_pubkey),
+            mint_b_pubkey: Pubkey::new_from_array(*mint_b_pubkey),
+            trade_fee_numerator: u64::from_le_bytes(*trade_fee_numerator),
+            trade_fee_denominator: u64::from_le_bytes(*trade_fee_denominator),
+            protocol_fee_numerator: u64::from_le_bytes(*protocol_fee_numerator),
+            fee_authority_pubkey: Pubkey::new_from_array(*fee_authority_pubkey),
+            protocol_fees_a: u64::from_le_bytes(*protocol_fees_a),
+            protocol_fees_b: u64::from_le_bytes(*protocol_fees_b),
+            bump_seed: bump_seed[0],
+            reserve_a: u64::from_le_bytes(*reserve_a),
+            reserve_b: u64::from_le_bytes(*reserve_b),
+            price_a_cumulative: u128::from_le_bytes(*price_a_cumulative),
+            price_b_cumulative: u128::from_le_bytes(*price_b_cumulative),
+            last_update_timestamp: i64::from_le_bytes(*last_update_timestamp),
+            curve_type: CurveType::try_from(curve_type[0])?,
+            amplification: u64::from_le_bytes(*amplification),
+            ramp_start_amplification: u64::from_le_bytes(*ramp_start_amplification),
+            target_amplification: u64::from_le_bytes(*target_amplification),
+            ramp_start_timestamp: i64::from_le_bytes(*ramp_start_timestamp),
+            ramp_end_timestamp: i64::from_le_bytes(*ramp_end_timestamp),
+            weight_a: u64::from_le_bytes(*weight_a),
+            weight_b: u64::from_le_bytes(*weight_b),
+            start_weight_a: u64::from_le_bytes(*start_weight_a),
+            end_weight_a: u64::from_le_bytes(*end_weight_a),
+            weights_start_timestamp: i64::from_le_bytes(*weights_start_timestamp),
+            weights_end_timestamp: i64::from_le_bytes(*weights_end_timestamp),
+            tick_spacing: u16::from_le_bytes(*tick_spacing),
+            sqrt_price: u128::from_le_bytes(*sqrt_price),
+            tick_current: i32::from_le_bytes(*tick_current),
+            liquidity: u64::from_le_bytes(*liquidity),
+            fee_growth_global_a: u128::from_le_bytes(*fee_growth_global_a),
+            fee_growth_global_b: u128::from_le_bytes(*fee_growth_global_b),
+            oracle_pubkey: Pubkey::new_from_array(*oracle_pubkey),
+            max_oracle_staleness: u64::from_le_bytes(*max_oracle_staleness),
+            oracle_price: u128::from_le_bytes(*oracle_price),
+            slippage_factor: u64::from_le_bytes(*slippage_factor),
+            target_a: u64::from_le_bytes(*target_a),
+            target_b: u64::from_le_bytes(*target_b),
+            max_price_deviation_bps: u64::from_le_bytes(*max_price_deviation_bps),
         })
     }
 
@@ -54,23 +527,630 @@ impl Pack for AMM {
         let (
             is_initialized_dst,
             initializer_account_pubkey_dst,
-            busd_token_account_pubkey_dst,
-            bstock_token_account_pubkey_dst,
-            bstocklqdy_token_account_pubkey_dst,
-        ) = mut_array_refs![dst, 1, 32, 32, 32, 32];
+            token_a_account_pubkey_dst,
+            token_b_account_pubkey_dst,
+            lp_mint_pubkey_dst,
+            mint_a_pubkey_dst,
+            mint_b_pubkey_dst,
+            trade_fee_numerator_dst,
+            trade_fee_denominator_dst,
+            protocol_fee_numerator_dst,
+            fee_authority_pubkey_dst,
+            protocol_fees_a_dst,
+            protocol_fees_b_dst,
+            bump_seed_dst,
+            reserve_a_dst,
+            reserve_b_dst,
+            price_a_cumulative_dst,
+            price_b_cumulative_dst,
+            last_update_timestamp_dst,
+            curve_type_dst,
+            amplification_dst,
+            ramp_start_amplification_dst,
+            target_amplification_dst,
+            ramp_start_timestamp_dst,
+            ramp_end_timestamp_dst,
+            weight_a_dst,
+            weight_b_dst,
+            start_weight_a_dst,
+            end_weight_a_dst,
+            weights_start_timestamp_dst,
+            weights_end_timestamp_dst,
+            tick_spacing_dst,
+            sqrt_price_dst,
+            tick_current_dst,
+            liquidity_dst,
+            fee_growth_global_a_dst,
+            fee_growth_global_b_dst,
+            oracle_pubkey_dst,
+            max_oracle_staleness_dst,
+            oracle_price_dst,
+            slippage_factor_dst,
+            target_a_dst,
+            target_b_dst,
+            max_price_deviation_bps_dst,
+        ) = mut_array_refs![
+            dst, 1, 32, 32, 32, 32, 32, 32, 8, 8, 8, 32, 8, 8, 1, 8, 8, 16, 16, 8, 1, 8, 8, 8, 8,
+            8, 8, 8, 8, 8, 8, 8, 2, 16, 4, 8, 16, 16, 32, 8, 16, 8, 8, 8, 8
+        ];
 
         let AMM {
             is_initialized,
             initializer_account_pubkey,
-            busd_token_account_pubkey,
-            bstock_token_account_pubkey,
-            bstocklqdy_token_account_pubkey,
+            token_a_account_pubkey,
+            token_b_account_pubkey,
+            lp_mint_pubkey,
+            mint_a_pubkey,
+            mint_b_pubkey,
+            trade_fee_numerator,
+            trade_fee_denominator,
+            protocol_fee_numerator,
+            fee_authority_pubkey,
+            protocol_fees_a,
+            protocol_fees_b,
+            bump_seed,
+            reserve_a,
+            reserve_b,
+            price_a_cumulative,
+            price_b_cumulative,
+            last_update_timestamp,
+            curve_type,
+            amplification,
+            ramp_start_amplification,
+            target_amplification,
+            ramp_start_timestamp,
+            ramp_end_timestamp,
+            weight_a,
+            weight_b,
+            start_weight_a,
+            end_weight_a,
+            weights_start_timestamp,
+            weights_end_timestamp,
+            tick_spacing,
+            sqrt_price,
+            tick_current,
+            liquidity,
+            fee_growth_global_a,
+            fee_growth_global_b,
+            oracle_pubkey,
+            max_oracle_staleness,
+            oracle_price,
+            slippage_factor,
+            target_a,
+            target_b,
+            max_price_deviation_bps,
         } = self;
 
         is_initialized_dst[0] = *is_initialized as u8;
         initializer_account_pubkey_dst.copy_from_slice(initializer_account_pubkey.as_ref());
-        busd_token_account_pubkey_dst.copy_from_slice(busd_token_account_pubkey.as_ref());
-        bstock_token_account_pubkey_dst.copy_from_slice(bstock_token_account_pubkey.as_ref());
-        bstocklqdy_token_account_pubkey_dst.copy_from_slice(bstocklqdy_token_account_pubkey.as_ref());
+        token_a_account_pubkey_dst.copy_from_slice(token_a_account_pubkey.as_ref());
+        token_b_account_pubkey_dst.copy_from_slice(token_b_account_pubkey.as_ref());
+        lp_mint_pubkey_dst.copy_from_slice(lp_mint_pubkey.as_ref());
+        mint_a_pubkey_dst.copy_from_slice(mint_a_pubkey.as_ref());
+        mint_b_pubkey_dst.copy_from_slice(mint_b_pubkey.as_ref());
+        *trade_fee_numerator_dst = trade_fee_numerator.to_le_bytes();
+        *trade_fee_denominator_dst = trade_fee_denominator.to_le_bytes();
+        *protocol_fee_numerator_dst = protocol_fee_numerator.to_le_bytes();
+        fee_authority_pubkey_dst.copy_from_slice(fee_authority_pubkey.as_ref());
+        *protocol_fees_a_dst = protocol_fees_a.to_le_bytes();
+        *protocol_fees_b_dst = protocol_fees_b.to_le_bytes();
+        bump_seed_dst[0] = *bump_seed;
+        *reserve_a_dst = reserve_a.to_le_bytes();
+        *reserve_b_dst = reserve_b.to_le_bytes();
+        *price_a_cumulative_dst = price_a_cumulative.to_le_bytes();
+        *price_b_cumulative_dst = price_b_cumulative.to_le_bytes();
+        *last_update_timestamp_dst = last_update_timestamp.to_le_bytes();
+        curve_type_dst[0] = *curve_type as u8;
+        *amplification_dst = amplification.to_le_bytes();
+        *ramp_start_amplification_dst = ramp_start_amplification.to_le_bytes();
+        *target_amplification_dst = target_amplification.to_le_bytes();
+        *ramp_start_timestamp_dst = ramp_start_timestamp.to_le_bytes();
+        *ramp_end_timestamp_dst = ramp_end_timestamp.to_le_bytes();
+        *weight_a_dst = weight_a.to_le_bytes();
+        *weight_b_dst = weight_b.to_le_bytes();
+        *start_weight_a_dst = start_weight_a.to_le_bytes();
+        *end_weight_a_dst = end_weight_a.to_le_bytes();
+        *weights_start_timestamp_dst = weights_start_timestamp.to_le_bytes();
+        *weights_end_timestamp_dst = weights_end_timestamp.to_le_bytes();
+        *tick_spacing_dst = tick_spacing.to_le_bytes();
+        *sqrt_price_dst = sqrt_price.to_le_bytes();
+        *tick_current_dst = tick_current.to_le_bytes();
+        *liquidity_dst = liquidity.to_le_bytes();
+        *fee_growth_global_a_dst = fee_growth_global_a.to_le_bytes();
+        *fee_growth_global_b_dst = fee_growth_global_b.to_le_bytes();
+        oracle_pubkey_dst.copy_from_slice(oracle_pubkey.as_ref());
+        *max_oracle_staleness_dst = max_oracle_staleness.to_le_bytes();
+        *oracle_price_dst = oracle_price.to_le_bytes();
+        *slippage_factor_dst = slippage_factor.to_le_bytes();
+        *target_a_dst = target_a.to_le_bytes();
+        *target_b_dst = target_b.to_le_bytes();
+        *max_price_deviation_bps_dst = max_price_deviation_bps.to_le_bytes();
+    }
+}
+
+/// A reference price of token A in token B published by `authority_pubkey`, read by the proactive market
+/// maker pools anchored to this account
+pub struct Oracle {
+    pub is_initialized: bool,
+    pub authority_pubkey: Pubkey,
+    /// UQ64.64 fixed-point number, never 0
+    pub price: u128,
+    /// Unix timestamp at which `price` was published
+    pub timestamp: i64,
+}
+
+impl Sealed for Oracle {}
+
+impl IsInitialized for Oracle {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Oracle {
+    const LEN: usize = 57;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, Oracle::LEN];
+        let (is_initialized, authority_pubkey, price, timestamp) = array_refs![src, 1, 32, 16, 8];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(Oracle {
+            is_initialized,
+            authority_pubkey: Pubkey::new_from_array(*authority_pubkey),
+            price: u128::from_le_bytes(*price),
+            timestamp: i64::from_le_bytes(*timestamp),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, Oracle::LEN];
+        let (is_initialized_dst, authority_pubkey_dst, price_dst, timestamp_dst) =
+            mut_array_refs![dst, 1, 32, 16, 8];
+
+        let Oracle {
+            is_initialized,
+            authority_pubkey,
+            price,
+            timestamp,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        authority_pubkey_dst.copy_from_slice(authority_pubkey.as_ref());
+        *price_dst = price.to_le_bytes();
+        *timestamp_dst = timestamp.to_le_bytes();
+    }
+}
+
+/// Number of observations kept by an observation account before the oldest ones are overwritten
+pub const OBSERVATIONS_CAPACITY: usize = 64;
+
+/// The AMM's cumulative prices as of `timestamp`
+#[derive(Clone, Copy, Default)]
+pub struct Observation {
+    pub timestamp: i64,
+    pub price_a_cumulative: u128,
+    pub price_b_cumulative: u128,
+}
+
+impl Observation {
+    const LEN: usize = 40;
+}
+
+/// Ring buffer of the cumulative prices of the pool stored in `amm_pubkey`, written at most once per slot
+pub struct Observations {
+    pub is_initialized: bool,
+    pub amm_pubkey: Pubkey,
+    pub last_slot: u64,
+    /// Number of observations ever written, the newest one is at `(count - 1) % OBSERVATIONS_CAPACITY`
+    pub count: u64,
+    pub observations: [Observation; OBSERVATIONS_CAPACITY],
+}
+
+impl Observations {
+    /// Writes `observation` over the oldest one, unless an observation was already written in `slot`.
+    /// Returns whether it was written.
+    pub fn record(&mut self, slot: u64, observation: Observation) -> bool {
+        if self.count > 0 && self.last_slot == slot {
+            return false;
+        }
+        self.observations[(self.count % OBSERVATIONS_CAPACITY as u64) as usize] = observation;
+        self.count += 1;
+        self.last_slot = slot;
+        true
+    }
+
+    /// Finds the newest observation taken at or before `timestamp`
+    pub fn latest_at_or_before(&self, timestamp: i64) -> Option<&Observation> {
+        let len = self.count.min(OBSERVATIONS_CAPACITY as u64) as usize;
+        self.observations[..len]
+            .iter()
+            .filter(|observation| observation.timestamp <= timestamp)
+            .max_by_key(|observation| observation.timestamp)
+    }
+
+    /// Finds the oldest observation taken after `timestamp`
+    pub fn earliest_after(&self, timestamp: i64) -> Option<&Observation> {
+        let len = self.count.min(OBSERVATIONS_CAPACITY as u64) as usize;
+        self.observations[..len]
+            .iter()
+            .filter(|observation| observation.timestamp > timestamp)
+            .min_by_key(|observation| observation.timestamp)
+    }
+
+    /// Computes the cumulative prices as of `timestamp` by interpolating between the observations around it,
+    /// or between the newest one and `current`, the AMM's cumulative prices as of now. Fails unless an
+    /// observation was taken at or before `timestamp`.
+    pub fn cumulative_prices_at(
+        &self,
+        timestamp: i64,
+        current: &Observation,
+    ) -> Option<(u128, u128)> {
+        let before = self.latest_at_or_before(timestamp)?;
+        let after = self.earliest_after(timestamp).unwrap_or(current);
+        if before.timestamp == timestamp || after.timestamp <= before.timestamp {
+            return Some((before.price_a_cumulative, before.price_b_cumulative));
+        }
+        let elapsed = (timestamp - before.timestamp) as u128;
+        let span = (after.timestamp - before.timestamp) as u128;
+        let interpolate = |before: u128, after: u128| {
+            let delta = curve::mul_div(after.wrapping_sub(before), elapsed, span, false)?;
+            Some(before.wrapping_add(delta))
+        };
+        Some((
+            interpolate(before.price_a_cumulative, after.price_a_cumulative)?,
+            interpolate(before.price_b_cumulative, after.price_b_cumulative)?,
+        ))
+    }
+}
+
+impl Sealed for Observations {}
+
+impl IsInitialized for Observations {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Observations {
+    const LEN: usize = 49 + OBSERVATIONS_CAPACITY * Observation::LEN;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, Observations::LEN];
+        let (is_initialized, amm_pubkey, last_slot, count, observations_src) =
+            array_refs![src, 1, 32, 8, 8, OBSERVATIONS_CAPACITY * Observation::LEN];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        let mut observations = [Observation::default(); OBSERVATIONS_CAPACITY];
+        for (observation, src) in observations
+            .iter_mut()
+            .zip(observations_src.chunks_exact(Observation::LEN))
+        {
+            let src = array_ref![src, 0, Observation::LEN];
+            let (timestamp, price_a_cumulative, price_b_cumulative) = array_refs![src, 8, 16, 16];
+            *observation = Observation {
+                timestamp: i64::from_le_bytes(*timestamp),
+                price_a_cumulative: u128::from_le_bytes(*price_a_cumulative),
+                price_b_cumulative: u128::from_le_bytes(*price_b_cumulative),
+            };
+        }
+
+        Ok(Observations {
+            is_initialized,
+            amm_pubkey: Pubkey::new_from_array(*amm_pubkey),
+            last_slot: u64::from_le_bytes(*last_slot),
+            count: u64::from_le_bytes(*count),
+            observations,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, Observations::LEN];
+        let (is_initialized_dst, amm_pubkey_dst, last_slot_dst, count_dst, observations_dst) =
+            mut_array_refs![dst, 1, 32, 8, 8, OBSERVATIONS_CAPACITY * Observation::LEN];
+
+        let Observations {
+            is_initialized,
+            amm_pubkey,
+            last_slot,
+            count,
+            observations,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        amm_pubkey_dst.copy_from_slice(amm_pubkey.as_ref());
+        *last_slot_dst = last_slot.to_le_bytes();
+        *count_dst = count.to_le_bytes();
+        for (observation, dst) in observations
+            .iter()
+            .zip(observations_dst.chunks_exact_mut(Observation::LEN))
+        {
+            let dst = array_mut_ref![dst, 0, Observation::LEN];
+            let (timestamp_dst, price_a_cumulative_dst, price_b_cumulative_dst) =
+                mut_array_refs![dst, 8, 16, 16];
+            *timestamp_dst = observation.timestamp.to_le_bytes();
+            *price_a_cumulative_dst = observation.price_a_cumulative.to_le_bytes();
+            *price_b_cumulative_dst = observation.price_b_cumulative.to_le_bytes();
+        }
+    }
+}
+
+/// Number of initialized ticks a ticks account holds, i.e. of distinct position bounds in a pool
+pub const TICKS_CAPACITY: usize = 128;
+
+/// A tick some position starts or ends at
+#[derive(Clone, Copy, Default)]
+pub struct Tick {
+    pub index: i32,
+    /// Liquidity of the positions starting or ending at this tick, which keeps it initialized while positive
+    pub liquidity_gross: u64,
+    /// Liquidity added to the pool when the price crosses this tick upwards, removed when it crosses downwards
+    pub liquidity_net: i128,
+    /// LP fees earned per unit of liquidity on the other side of this tick from the current price, in
+    /// token A and token B. Flipped to `fee_growth_global - fee_growth_outside` whenever the price crosses it.
+    pub fee_growth_outside_a: u128,
+    pub fee_growth_outside_b: u128,
+}
+
+impl Tick {
+    const LEN: usize = 60;
+}
+
+/// The initialized ticks of the concentrated-liquidity pool stored in `amm_pubkey`
+pub struct Ticks {
+    pub is_initialized: bool,
+    pub amm_pubkey: Pubkey,
+    /// Sorted by index, at most [TICKS_CAPACITY](constant.TICKS_CAPACITY.html) of them
+    pub ticks: Vec<Tick>,
+}
+
+impl Ticks {
+    /// Finds the initialized tick at `index`
+    pub fn get(&self, index: i32) -> Option<&Tick> {
+        let position = self
+            .ticks
+            .binary_search_by_key(&index, |tick| tick.index)
+            .ok()?;
+        self.ticks.get(position)
+    }
+
+    /// Finds the initialized tick at `index`, mutably
+    pub fn get_mut(&mut self, index: i32) -> Option<&mut Tick> {
+        let position = self
+            .ticks
+            .binary_search_by_key(&index, |tick| tick.index)
+            .ok()?;
+        self.ticks.get_mut(position)
+    }
+
+    /// Initializes `tick`, unless its index already is or the account is full. Returns whether it was inserted.
+    pub fn insert(&mut self, tick: Tick) -> bool {
+        if self.ticks.len() >= TICKS_CAPACITY {
+            return false;
+        }
+        match self
+            .ticks
+            .binary_search_by_key(&tick.index, |tick| tick.index)
+        {
+            Ok(_) => false,
+            Err(position) => {
+                self.ticks.insert(position, tick);
+                true
+            }
+        }
+    }
+
+    /// Uninitializes the tick at `index`
+    pub fn remove(&mut self, index: i32) {
+        if let Ok(position) = self.ticks.binary_search_by_key(&index, |tick| tick.index) {
+            self.ticks.remove(position);
+        }
+    }
+
+    /// Finds the next initialized tick a trade crosses from `tick_current`: the highest one at or below it if the
+    /// price goes down (`a_to_b`), the lowest one above it otherwise
+    pub fn next_initialized(&self, tick_current: i32, a_to_b: bool) -> Option<Tick> {
+        let position = self
+            .ticks
+            .partition_point(|tick| tick.index <= tick_current);
+        if a_to_b {
+            position.checked_sub(1).map(|position| self.ticks[position])
+        } else {
+            self.ticks.get(position).copied()
+        }
+    }
+}
+
+impl Sealed for Ticks {}
+
+impl IsInitialized for Ticks {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Ticks {
+    const LEN: usize = 41 + TICKS_CAPACITY * Tick::LEN;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, Ticks::LEN];
+        let (is_initialized, amm_pubkey, count, ticks_src) =
+            array_refs![src, 1, 32, 8, TICKS_CAPACITY * Tick::LEN];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let count = u64::from_le_bytes(*count) as usize;
+        if count > TICKS_CAPACITY {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let ticks = ticks_src
+            .chunks_exact(Tick::LEN)
+            .take(count)
+            .map(|src| {
+                let src = array_ref![src, 0, Tick::LEN];
+                let (
+                    index,
+                    liquidity_gross,
+                    liquidity_net,
+                    fee_growth_outside_a,
+                    fee_growth_outside_b,
+                ) = array_refs![src, 4, 8, 16, 16, 16];
+                Tick {
+                    index: i32::from_le_bytes(*index),
+                    liquidity_gross: u64::from_le_bytes(*liquidity_gross),
+                    liquidity_net: i128::from_le_bytes(*liquidity_net),
+                    fee_growth_outside_a: u128::from_le_bytes(*fee_growth_outside_a),
+                    fee_growth_outside_b: u128::from_le_bytes(*fee_growth_outside_b),
+                }
+            })
+            .collect();
+
+        Ok(Ticks {
+            is_initialized,
+            amm_pubkey: Pubkey::new_from_array(*amm_pubkey),
+            ticks,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, Ticks::LEN];
+        let (is_initialized_dst, amm_pubkey_dst, count_dst, ticks_dst) =
+            mut_array_refs![dst, 1, 32, 8, TICKS_CAPACITY * Tick::LEN];
+
+        let Ticks {
+            is_initialized,
+            amm_pubkey,
+            ticks,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        amm_pubkey_dst.copy_from_slice(amm_pubkey.as_ref());
+        *count_dst = (ticks.len() as u64).to_le_bytes();
+        ticks_dst.fill(0);
+        for (tick, dst) in ticks.iter().zip(ticks_dst.chunks_exact_mut(Tick::LEN)) {
+            let dst = array_mut_ref![dst, 0, Tick::LEN];
+            let (
+                index_dst,
+                liquidity_gross_dst,
+                liquidity_net_dst,
+                fee_growth_outside_a_dst,
+                fee_growth_outside_b_dst,
+            ) = mut_array_refs![dst, 4, 8, 16, 16, 16];
+            *index_dst = tick.index.to_le_bytes();
+            *liquidity_gross_dst = tick.liquidity_gross.to_le_bytes();
+            *liquidity_net_dst = tick.liquidity_net.to_le_bytes();
+            *fee_growth_outside_a_dst = tick.fee_growth_outside_a.to_le_bytes();
+            *fee_growth_outside_b_dst = tick.fee_growth_outside_b.to_le_bytes();
+        }
+    }
+}
+
+/// Liquidity provided by `owner_pubkey` to the concentrated-liquidity pool stored in `amm_pubkey`, while the
+/// price is between `tick_lower` and `tick_upper`
+pub struct Position {
+    pub is_initialized: bool,
+    pub amm_pubkey: Pubkey,
+    pub owner_pubkey: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u64,
+    /// LP fees earned per unit of liquidity within the position's range, as of the last time its fees were
+    /// counted into `fees_owed_a` and `fees_owed_b`
+    pub fee_growth_inside_a_last: u128,
+    pub fee_growth_inside_b_last: u128,
+    pub fees_owed_a: u64,
+    pub fees_owed_b: u64,
+}
+
+impl Sealed for Position {}
+
+impl IsInitialized for Position {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
     }
-}
\ No newline at end of file
+}
+
+impl Pack for Position {
+    const LEN: usize = 129;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, Position::LEN];
+        let (
+            is_initialized,
+            amm_pubkey,
+            owner_pubkey,
+            tick_lower,
+            tick_upper,
+            liquidity,
+            fee_growth_inside_a_last,
+            fee_growth_inside_b_last,
+            fees_owed_a,
+            fees_owed_b,
+        ) = array_refs![src, 1, 32, 32, 4, 4, 8, 16, 16, 8, 8];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(Position {
+            is_initialized,
+            amm_pubkey: Pubkey::new_from_array(*amm_pubkey),
+            owner_pubkey: Pubkey::new_from_array(*owner_pubkey),
+            tick_lower: i32::from_le_bytes(*tick_lower),
+            tick_upper: i32::from_le_bytes(*tick_upper),
+            liquidity: u64::from_le_bytes(*liquidity),
+            fee_growth_inside_a_last: u128::from_le_bytes(*fee_growth_inside_a_last),
+            fee_growth_inside_b_last: u128::from_le_bytes(*fee_growth_inside_b_last),
+            fees_owed_a: u64::from_le_bytes(*fees_owed_a),
+            fees_owed_b: u64::from_le_bytes(*fees_owed_b),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, Position::LEN];
+        let (
+            is_initialized_dst,
+            amm_pubkey_dst,
+            owner_pubkey_dst,
+            tick_lower_dst,
+            tick_upper_dst,
+            liquidity_dst,
+            fee_growth_inside_a_last_dst,
+            fee_growth_inside_b_last_dst,
+            fees_owed_a_dst,
+            fees_owed_b_dst,
+        ) = mut_array_refs![dst, 1, 32, 32, 4, 4, 8, 16, 16, 8, 8];
+
+        let Position {
+            is_initialized,
+            amm_pubkey,
+            owner_pubkey,
+            tick_lower,
+            tick_upper,
+            liquidity,
+            fee_growth_inside_a_last,
+            fee_growth_inside_b_last,
+            fees_owed_a,
+            fees_owed_b,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        amm_pubkey_dst.copy_from_slice(amm_pubkey.as_ref());
+        owner_pubkey_dst.copy_from_slice(owner_pubkey.as_ref());
+        *tick_lower_dst = tick_lower.to_le_bytes();
+        *tick_upper_dst = tick_upper.to_le_bytes();
+        *liquidity_dst = liquidity.to_le_bytes();
+        *fee_growth_inside_a_last_dst = fee_growth_inside_a_last.to_le_bytes();
+        *fee_growth_inside_b_last_dst = fee_growth_inside_b_last.to_le_bytes();
+        *fees_owed_a_dst = fees_owed_a.to_le_bytes();
+        *fees_owed_b_dst = fees_owed_b.to_le_bytes();
+    }
+}
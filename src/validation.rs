@@ -0,0 +1,132 @@
+// inside validation.rs
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    program_pack::Pack, pubkey::Pubkey, system_program,
+};
+
+use spl_token::state::{Account as TokenAccount, Mint};
+
+use crate::{
+    error::AMMError,
+    state::{Oracle, AMM},
+};
+
+/// Fails unless `account` signed the transaction
+pub fn check_signer(account: &AccountInfo) -> ProgramResult {
+    if !account.is_signer {
+        return Err(AMMError::MissingSignature.into());
+    }
+    Ok(())
+}
+
+/// Fails unless `token_program` is the SPL Token program
+pub fn check_token_program(token_program: &AccountInfo) -> ProgramResult {
+    if *token_program.key != spl_token::id() {
+        return Err(AMMError::IncorrectTokenProgramId.into());
+    }
+    Ok(())
+}
+
+/// Fails unless `system_program` is the System program
+pub fn check_system_program(system_program: &AccountInfo) -> ProgramResult {
+    if *system_program.key != system_program::id() {
+        return Err(AMMError::IncorrectSystemProgramId.into());
+    }
+    Ok(())
+}
+
+/// Fails unless `amm_account` is owned by this program
+pub fn check_amm_owner(amm_account: &AccountInfo, program_id: &Pubkey) -> ProgramResult {
+    if amm_account.owner != program_id {
+        return Err(AMMError::InvalidAMMAccountOwner.into());
+    }
+    Ok(())
+}
+
+/// Unpacks an initialized AMM account owned by this program
+pub fn unpack_amm(amm_account: &AccountInfo, program_id: &Pubkey) -> Result<AMM, ProgramError> {
+    check_amm_owner(amm_account, program_id)?;
+    AMM::unpack(&amm_account.data.borrow())
+}
+
+/// Unpacks an initialized oracle account owned by this program
+pub fn unpack_oracle(
+    oracle_account: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<Oracle, ProgramError> {
+    if oracle_account.owner != program_id {
+        return Err(AMMError::InvalidOracleAccount.into());
+    }
+    Oracle::unpack(&oracle_account.data.borrow())
+}
+
+/// Unpacks a token account owned by the SPL Token program, whatever its mint
+pub fn unpack_token_account(account: &AccountInfo) -> Result<TokenAccount, ProgramError> {
+    if *account.owner != spl_token::id() {
+        return Err(AMMError::NotOwnedByTokenProgram.into());
+    }
+    TokenAccount::unpack(&account.data.borrow())
+}
+
+/// Unpacks a token account owned by the SPL Token program and fails unless it holds `mint`
+pub fn unpack_token_account_with_mint(
+    account: &AccountInfo,
+    mint: &Pubkey,
+) -> Result<TokenAccount, ProgramError> {
+    let token_account = unpack_token_account(account)?;
+    if token_account.mint != *mint {
+        return Err(AMMError::InvalidMint.into());
+    }
+    Ok(token_account)
+}
+
+/// Unpacks a mint owned by the SPL Token program
+pub fn unpack_mint(account: &AccountInfo) -> Result<Mint, ProgramError> {
+    if *account.owner != spl_token::id() {
+        return Err(AMMError::NotOwnedByTokenProgram.into());
+    }
+    Mint::unpack(&account.data.borrow())
+}
+
+/// Fails unless the passed token accounts are the ones recorded in the AMM
+pub fn check_pool_token_accounts(
+    amm_info: &AMM,
+    pda_token_a_account: &AccountInfo,
+    pda_token_b_account: &AccountInfo,
+) -> ProgramResult {
+    if amm_info.token_a_account_pubkey != *pda_token_a_account.key
+        || amm_info.token_b_account_pubkey != *pda_token_b_account.key
+    {
+        return Err(AMMError::IncorrectPoolTokenAccount.into());
+    }
+    Ok(())
+}
+
+/// Fails unless the passed LP mint is the one recorded in the AMM
+pub fn check_pool_mint(amm_info: &AMM, lp_mint: &AccountInfo) -> ProgramResult {
+    if amm_info.lp_mint_pubkey != *lp_mint.key {
+        return Err(AMMError::IncorrectPoolMint.into());
+    }
+    Ok(())
+}
+
+/// Fails unless `pda_account` is the pool's PDA
+pub fn check_pool_authority(pda_account: &AccountInfo, pda: &Pubkey) -> ProgramResult {
+    if pda_account.key != pda {
+        return Err(AMMError::InvalidPoolAuthority.into());
+    }
+    Ok(())
+}
+
+/// Fails if the same account was passed in two places
+pub fn check_distinct(accounts: &[&AccountInfo]) -> ProgramResult {
+    for (i, account) in accounts.iter().enumerate() {
+        if accounts[i + 1..]
+            .iter()
+            .any(|other| other.key == account.key)
+        {
+            return Err(AMMError::DuplicateAccounts.into());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,2020 @@
+use bpf_program_template::{
+    curve::{self, CurveType},
+    error::AMMError,
+    instruction, pmm,
+    processor::Processor,
+    state::{Observations, Oracle, Position, AMM},
+};
+use solana_program::{
+    clock::Clock,
+    instruction::{Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token::state::{Account as TokenAccount, Mint};
+use std::convert::TryInto;
+
+#[derive(Debug)]
+struct Pool {
+    amm: Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    vault_a: Pubkey,
+    vault_b: Pubkey,
+    lp_mint: Pubkey,
+    oracle: Option<Pubkey>,
+}
+
+async fn start() -> (ProgramTestContext, Pubkey) {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "bpf_program_template",
+        program_id,
+        processor!(Processor::process),
+    );
+    (program_test.start_with_context().await, program_id)
+}
+
+async fn send(
+    ctx: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let blockhash = ctx.banks_client.get_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&ctx.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&ctx.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    ctx.banks_client.process_transaction(transaction).await
+}
+
+async fn create_account(ctx: &mut ProgramTestContext, space: usize, owner: &Pubkey) -> Keypair {
+    let account = Keypair::new();
+    let lamports = Rent::default().minimum_balance(space);
+    let ix = system_instruction::create_account(
+        &ctx.payer.pubkey(),
+        &account.pubkey(),
+        lamports,
+        space as u64,
+        owner,
+    );
+    send(ctx, &[ix], &[&account]).await.unwrap();
+    account
+}
+
+async fn create_mint(
+    ctx: &mut ProgramTestContext,
+    authority: &Pubkey,
+    freeze_authority: Option<&Pubkey>,
+) -> Pubkey {
+    let mint = create_account(ctx, Mint::LEN, &spl_token::id()).await;
+    let ix = spl_token::instruction::initialize_mint(
+        &spl_token::id(),
+        &mint.pubkey(),
+        authority,
+        freeze_authority,
+        6,
+    )
+    .unwrap();
+    send(ctx, &[ix], &[]).await.unwrap();
+    mint.pubkey()
+}
+
+async fn create_token_account(
+    ctx: &mut ProgramTestContext,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Pubkey {
+    let account = create_account(ctx, TokenAccount::LEN, &spl_token::id()).await;
+    let ix = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        &account.pubkey(),
+        mint,
+        owner,
+    )
+    .unwrap();
+    send(ctx, &[ix], &[]).await.unwrap();
+    account.pubkey()
+}
+
+async fn mint_to(ctx: &mut ProgramTestContext, mint: &Pubkey, account: &Pubkey, amount: u64) {
+    let ix = spl_token::instruction::mint_to(
+        &spl_token::id(),
+        mint,
+        account,
+        &ctx.payer.pubkey(),
+        &[],
+        amount,
+    )
+    .unwrap();
+    send(ctx, &[ix], &[]).await.unwrap();
+}
+
+async fn balance(ctx: &mut ProgramTestContext, account: &Pubkey) -> u64 {
+    let account = ctx
+        .banks_client
+        .get_account(*account)
+        .await
+        .unwrap()
+        .unwrap();
+    TokenAccount::unpack(&account.data).unwrap().amount
+}
+
+async fn supply(ctx: &mut ProgramTestContext, mint: &Pubkey) -> u64 {
+    let account = ctx.banks_client.get_account(*mint).await.unwrap().unwrap();
+    Mint::unpack(&account.data).unwrap().supply
+}
+
+async fn amm_state(ctx: &mut ProgramTestContext, amm: &Pubkey) -> AMM {
+    let account = ctx.banks_client.get_account(*amm).await.unwrap().unwrap();
+    AMM::unpack(&account.data).unwrap()
+}
+
+/// Moves to the next slot, `seconds` after the current clock
+async fn advance_clock(ctx: &mut ProgramTestContext, seconds: i64) -> i64 {
+    let clock = ctx.banks_client.get_sysvar::<Clock>().await.unwrap();
+    ctx.warp_to_slot(clock.slot + 1).unwrap();
+    let mut next_clock = ctx.banks_client.get_sysvar::<Clock>().await.unwrap();
+    next_clock.unix_timestamp = clock.unix_timestamp + seconds;
+    ctx.set_sysvar(&next_clock);
+    next_clock.unix_timestamp
+}
+
+async fn observations_state(
+    ctx: &mut ProgramTestContext,
+    program_id: &Pubkey,
+    amm: &Pubkey,
+) -> Observations {
+    let (address, _) = instruction::find_observations_address(program_id, amm);
+    let account = ctx
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap();
+    Observations::unpack(&account.data).unwrap()
+}
+
+/// Runs a read-only instruction and returns its return data
+async fn return_data(
+    ctx: &mut ProgramTestContext,
+    ix: Instruction,
+) -> Result<Vec<u8>, TransactionError> {
+    let blockhash = ctx.banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&ctx.payer.pubkey()),
+        &[&ctx.payer],
+        blockhash,
+    );
+    loop {
+        let result = ctx
+            .banks_client
+            .process_transaction_with_metadata(transaction.clone())
+            .await
+            .unwrap();
+        match result.result {
+            // The previous transaction can still hold the payer's lock for a moment after it lands
+            Err(TransactionError::AccountInUse) => continue,
+            result => result?,
+        }
+        return Ok(result.metadata.unwrap().return_data.unwrap().data);
+    }
+}
+
+/// Runs a `QueryTwap` and decodes the average prices from its return data
+async fn query_twap(
+    ctx: &mut ProgramTestContext,
+    program_id: &Pubkey,
+    amm: &Pubkey,
+    window_seconds: u64,
+) -> Result<(u128, u128), TransactionError> {
+    let ix = instruction::query_twap(program_id, amm, window_seconds).unwrap();
+    let return_data = return_data(ctx, ix).await?;
+    Ok((
+        u128::from_le_bytes(return_data[..16].try_into().unwrap()),
+        u128::from_le_bytes(return_data[16..].try_into().unwrap()),
+    ))
+}
+
+/// Runs a `Quote` and decodes the amount out, fee and price impact from its return data
+async fn quote(
+    ctx: &mut ProgramTestContext,
+    program_id: &Pubkey,
+    amm: &Pubkey,
+    amount_in: u64,
+    a_to_b: bool,
+) -> (u64, u64, u64) {
+    let amm_info = amm_state(ctx, amm).await;
+    let oracle = Some(amm_info.oracle_pubkey).filter(|oracle| *oracle != Pubkey::default());
+    let ix = instruction::quote(
+        program_id,
+        amm,
+        amm_info.curve_type,
+        amount_in,
+        a_to_b,
+        oracle.as_ref(),
+    )
+    .unwrap();
+    let return_data = return_data(ctx, ix).await.unwrap();
+    (
+        u64::from_le_bytes(return_data[..8].try_into().unwrap()),
+        u64::from_le_bytes(return_data[8..16].try_into().unwrap()),
+        u64::from_le_bytes(return_data[16..].try_into().unwrap()),
+    )
+}
+
+fn amm_error(error: AMMError) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+}
+
+/// A user holding token A, token B and LP token accounts of a pool
+#[derive(Debug)]
+struct User {
+    keypair: Keypair,
+    token_a: Pubkey,
+    token_b: Pubkey,
+    lp: Pubkey,
+}
+
+async fn create_user_accounts(
+    ctx: &mut ProgramTestContext,
+    keypair: Keypair,
+    pool: &Pool,
+    amount_a: u64,
+    amount_b: u64,
+) -> User {
+    let owner = keypair.pubkey();
+    let token_a = create_token_account(ctx, &pool.mint_a, &owner).await;
+    let token_b = create_token_account(ctx, &pool.mint_b, &owner).await;
+    let lp = create_token_account(ctx, &pool.lp_mint, &owner).await;
+    mint_to(ctx, &pool.mint_a, &token_a, amount_a).await;
+    mint_to(ctx, &pool.mint_b, &token_b, amount_b).await;
+    User {
+        keypair,
+        token_a,
+        token_b,
+        lp,
+    }
+}
+
+async fn create_user(
+    ctx: &mut ProgramTestContext,
+    pool: &Pool,
+    amount_a: u64,
+    amount_b: u64,
+) -> User {
+    create_user_accounts(ctx, Keypair::new(), pool, amount_a, amount_b).await
+}
+
+/// Creates two fresh mints and a constant-product AMM over them, seeded with `initial_amount_a` and
+/// `initial_amount_b` by the returned initializer
+async fn create_pool(
+    ctx: &mut ProgramTestContext,
+    program_id: &Pubkey,
+    fee_authority: &Pubkey,
+    trade_fee_numerator: u64,
+    protocol_fee_numerator: u64,
+    initial_amount_a: u64,
+    initial_amount_b: u64,
+) -> Result<(Pool, User), BanksClientError> {
+    create_curve_pool(
+        ctx,
+        program_id,
+        fee_authority,
+        trade_fee_numerator,
+        protocol_fee_numerator,
+        initial_amount_a,
+        initial_amount_b,
+        Curve::new(CurveType::ConstantProduct),
+    )
+    .await
+}
+
+/// The curve parameters of `InitAMM`
+#[derive(Clone, Copy)]
+struct Curve {
+    curve_type: CurveType,
+    amplification: u64,
+    weight_a: u64,
+    end_weight_a: u64,
+    start_timestamp: i64,
+    end_timestamp: i64,
+    tick_spacing: u16,
+    slippage_factor: u64,
+    max_oracle_staleness: u64,
+    max_price_deviation_bps: u64,
+    oracle: Option<Pubkey>,
+}
+
+impl Curve {
+    /// A `curve_type` curve with evenly weighted tokens and no other parameters
+    fn new(curve_type: CurveType) -> Self {
+        Curve {
+            curve_type,
+            amplification: 0,
+            weight_a: curve::WEIGHT_ONE / 2,
+            end_weight_a: 0,
+            start_timestamp: 0,
+            end_timestamp: 0,
+            tick_spacing: 0,
+            slippage_factor: 0,
+            max_oracle_staleness: 0,
+            max_price_deviation_bps: 0,
+            oracle: None,
+        }
+    }
+}
+
+/// Same as `create_pool`, pricing trades against `curve`
+#[allow(clippy::too_many_arguments)]
+async fn create_curve_pool(
+    ctx: &mut ProgramTestContext,
+    program_id: &Pubkey,
+    fee_authority: &Pubkey,
+    trade_fee_numerator: u64,
+    protocol_fee_numerator: u64,
+    initial_amount_a: u64,
+    initial_amount_b: u64,
+    curve: Curve,
+) -> Result<(Pool, User), BanksClientError> {
+    init_pool(
+        ctx,
+        program_id,
+        fee_authority,
+        trade_fee_numerator,
+        protocol_fee_numerator,
+        initial_amount_a,
+        initial_amount_b,
+        curve,
+        None,
+    )
+    .await
+}
+
+/// Same as `create_curve_pool`, with an LP mint that `lp_freeze_authority` can freeze accounts of
+#[allow(clippy::too_many_arguments)]
+async fn init_pool(
+    ctx: &mut ProgramTestContext,
+    program_id: &Pubkey,
+    fee_authority: &Pubkey,
+    trade_fee_numerator: u64,
+    protocol_fee_numerator: u64,
+    initial_amount_a: u64,
+    initial_amount_b: u64,
+    curve: Curve,
+    lp_freeze_authority: Option<&Pubkey>,
+) -> Result<(Pool, User), BanksClientError> {
+    let payer = ctx.payer.pubkey();
+    let initializer = Keypair::new();
+    let fund_initializer =
+        system_instruction::transfer(&payer, &initializer.pubkey(), 1_000_000_000);
+    send(ctx, &[fund_initializer], &[]).await.unwrap();
+    let mint_a = create_mint(ctx, &payer, None).await;
+    let mint_b = create_mint(ctx, &payer, None).await;
+    let lp_mint = create_mint(ctx, &initializer.pubkey(), lp_freeze_authority).await;
+    let amm = create_account(ctx, AMM::LEN, program_id).await.pubkey();
+    let (vault_a, _) = instruction::find_pool_vault(program_id, &amm, &mint_a);
+    let (vault_b, _) = instruction::find_pool_vault(program_id, &amm, &mint_b);
+    let pool = Pool {
+        amm,
+        mint_a,
+        mint_b,
+        vault_a,
+        vault_b,
+        lp_mint,
+        oracle: curve.oracle,
+    };
+    let initializer =
+        create_user_accounts(ctx, initializer, &pool, initial_amount_a, initial_amount_b).await;
+
+    let ix = instruction::init_amm(
+        program_id,
+        &initializer.keypair.pubkey(),
+        &pool.mint_a,
+        &pool.mint_b,
+        &pool.lp_mint,
+        &pool.amm,
+        fee_authority,
+        &initializer.token_a,
+        &initializer.token_b,
+        &initializer.lp,
+        trade_fee_numerator,
+        1_000,
+        protocol_fee_numerator,
+        initial_amount_a,
+        initial_amount_b,
+        curve.curve_type,
+        curve.amplification,
+        curve.weight_a,
+        curve.end_weight_a,
+        curve.start_timestamp,
+        curve.end_timestamp,
+        curve.tick_spacing,
+        curve.slippage_factor,
+        curve.max_oracle_staleness,
+        curve.max_price_deviation_bps,
+        curve.oracle.as_ref(),
+    )
+    .unwrap();
+    send(ctx, &[ix], &[&initializer.keypair]).await?;
+
+    Ok((pool, initializer))
+}
+
+/// Creates a pool seeded with 1,000,000 of each token, charging a 0.3% fee
+async fn create_stable_pool(
+    ctx: &mut ProgramTestContext,
+    program_id: &Pubkey,
+    fee_authority: &Pubkey,
+    curve_type: CurveType,
+    amplification: u64,
+) -> Result<(Pool, User), BanksClientError> {
+    create_curve_pool(
+        ctx,
+        program_id,
+        fee_authority,
+        3,
+        0,
+        1_000_000,
+        1_000_000,
+        Curve {
+            amplification,
+            ..Curve::new(curve_type)
+        },
+    )
+    .await
+}
+
+fn provide_liquidity(
+    program_id: &Pubkey,
+    pool: &Pool,
+    user: &User,
+    amount_a: u64,
+    amount_b: u64,
+) -> Instruction {
+    instruction::provide_liquidity(
+        program_id,
+        &user.keypair.pubkey(),
+        &user.token_a,
+        &user.token_b,
+        &user.lp,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.lp_mint,
+        &pool.amm,
+        amount_a,
+        amount_b,
+    )
+    .unwrap()
+}
+
+fn claim_liquidity(program_id: &Pubkey, pool: &Pool, user: &User, amount: u64) -> Instruction {
+    instruction::claim_liquidity(
+        program_id,
+        &user.keypair.pubkey(),
+        &user.token_a,
+        &user.token_b,
+        &user.lp,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.lp_mint,
+        &pool.amm,
+        amount,
+    )
+    .unwrap()
+}
+
+fn swap(
+    program_id: &Pubkey,
+    pool: &Pool,
+    user: &User,
+    source: &Pubkey,
+    destination: &Pubkey,
+    amount_in: u64,
+    minimum_amount_out: u64,
+) -> Instruction {
+    instruction::swap(
+        program_id,
+        &user.keypair.pubkey(),
+        source,
+        destination,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.amm,
+        amount_in,
+        minimum_amount_out,
+        pool.oracle.as_ref(),
+    )
+    .unwrap()
+}
+
+fn close_amm(program_id: &Pubkey, pool: &Pool, initializer: &User) -> Instruction {
+    instruction::close_amm(
+        program_id,
+        &initializer.keypair.pubkey(),
+        &initializer.token_a,
+        &initializer.token_b,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.lp_mint,
+        &pool.amm,
+    )
+    .unwrap()
+}
+
+fn increase_liquidity(
+    program_id: &Pubkey,
+    pool: &Pool,
+    user: &User,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u64,
+) -> Instruction {
+    instruction::increase_liquidity(
+        program_id,
+        &user.keypair.pubkey(),
+        &user.token_a,
+        &user.token_b,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.amm,
+        tick_lower,
+        tick_upper,
+        liquidity,
+        u64::MAX,
+        u64::MAX,
+    )
+    .unwrap()
+}
+
+fn collect_fees(
+    program_id: &Pubkey,
+    pool: &Pool,
+    user: &User,
+    tick_lower: i32,
+    tick_upper: i32,
+) -> Instruction {
+    instruction::collect_fees(
+        program_id,
+        &user.keypair.pubkey(),
+        &user.token_a,
+        &user.token_b,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.amm,
+        tick_lower,
+        tick_upper,
+    )
+    .unwrap()
+}
+
+async fn position_state(
+    ctx: &mut ProgramTestContext,
+    program_id: &Pubkey,
+    pool: &Pool,
+    user: &User,
+    tick_lower: i32,
+    tick_upper: i32,
+) -> Position {
+    let (address, _) = instruction::find_position_address(
+        program_id,
+        &pool.amm,
+        &user.keypair.pubkey(),
+        tick_lower,
+        tick_upper,
+    );
+    let account = ctx
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap();
+    Position::unpack(&account.data).unwrap()
+}
+
+#[tokio::test]
+async fn test_pool_lifecycle() {
+    let (mut ctx, program_id) = start().await;
+    let payer = ctx.payer.pubkey();
+    let (pool, initializer) =
+        create_pool(&mut ctx, &program_id, &payer, 3, 0, 1_000_000, 4_000_000)
+            .await
+            .unwrap();
+
+    let state = amm_state(&mut ctx, &pool.amm).await;
+    let (pda, bump_seed) = instruction::find_pool_authority(&program_id, &pool.amm);
+    assert_eq!(state.mint_a_pubkey, pool.mint_a);
+    assert_eq!(state.mint_b_pubkey, pool.mint_b);
+    assert_eq!(state.bump_seed, bump_seed);
+    assert_eq!(state.token_a_account_pubkey, pool.vault_a);
+    assert_eq!(state.token_b_account_pubkey, pool.vault_b);
+    for vault in [pool.vault_a, pool.vault_b] {
+        let vault = ctx.banks_client.get_account(vault).await.unwrap().unwrap();
+        let vault = TokenAccount::unpack(&vault.data).unwrap();
+        assert_eq!(vault.owner, pda);
+        assert!(vault.delegate.is_none());
+        assert!(vault.close_authority.is_none());
+    }
+    assert_eq!(balance(&mut ctx, &pool.vault_a).await, 1_000_000);
+    assert_eq!(balance(&mut ctx, &pool.vault_b).await, 4_000_000);
+    assert_eq!(balance(&mut ctx, &initializer.token_a).await, 0);
+    assert_eq!(balance(&mut ctx, &initializer.token_b).await, 0);
+    assert_eq!(balance(&mut ctx, &initializer.lp).await, 1_999_000);
+    assert_eq!(supply(&mut ctx, &pool.lp_mint).await, 1_999_000);
+
+    let provider = create_user(&mut ctx, &pool, 100_000, 500_000).await;
+    let ix = provide_liquidity(&program_id, &pool, &provider, 100_000, 500_000);
+    send(&mut ctx, &[ix], &[&provider.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &pool.vault_a).await, 1_100_000);
+    assert_eq!(balance(&mut ctx, &pool.vault_b).await, 4_400_000);
+    assert_eq!(balance(&mut ctx, &provider.token_a).await, 0);
+    assert_eq!(balance(&mut ctx, &provider.token_b).await, 100_000);
+    assert_eq!(balance(&mut ctx, &provider.lp).await, 200_000);
+    assert_eq!(supply(&mut ctx, &pool.lp_mint).await, 2_199_000);
+
+    let trader = create_user(&mut ctx, &pool, 10_000, 50_000).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        39_521,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &trader.token_a).await, 0);
+    assert_eq!(balance(&mut ctx, &trader.token_b).await, 89_521);
+    assert_eq!(balance(&mut ctx, &pool.vault_a).await, 1_110_000);
+    assert_eq!(balance(&mut ctx, &pool.vault_b).await, 4_360_479);
+
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_b,
+        &trader.token_a,
+        50_000,
+        12_546,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &trader.token_a).await, 12_546);
+    assert_eq!(balance(&mut ctx, &trader.token_b).await, 39_521);
+    assert_eq!(balance(&mut ctx, &pool.vault_a).await, 1_097_454);
+    assert_eq!(balance(&mut ctx, &pool.vault_b).await, 4_410_479);
+
+    let ix = claim_liquidity(&program_id, &pool, &provider, 200_000);
+    send(&mut ctx, &[ix], &[&provider.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &provider.token_a).await, 99_768);
+    assert_eq!(balance(&mut ctx, &provider.token_b).await, 500_952);
+    assert_eq!(balance(&mut ctx, &provider.lp).await, 0);
+    assert_eq!(balance(&mut ctx, &pool.vault_a).await, 997_686);
+    assert_eq!(balance(&mut ctx, &pool.vault_b).await, 4_009_527);
+
+    let ix = claim_liquidity(&program_id, &pool, &initializer, 1_999_000);
+    send(&mut ctx, &[ix], &[&initializer.keypair])
+        .await
+        .unwrap();
+    assert_eq!(balance(&mut ctx, &initializer.token_a).await, 997_187);
+    assert_eq!(balance(&mut ctx, &initializer.token_b).await, 4_007_522);
+    assert_eq!(balance(&mut ctx, &initializer.lp).await, 0);
+    assert_eq!(supply(&mut ctx, &pool.lp_mint).await, 0);
+    assert_eq!(balance(&mut ctx, &pool.vault_a).await, 499);
+    assert_eq!(balance(&mut ctx, &pool.vault_b).await, 2_005);
+
+    let ix = close_amm(&program_id, &pool, &initializer);
+    send(&mut ctx, &[ix], &[&initializer.keypair])
+        .await
+        .unwrap();
+    assert_eq!(balance(&mut ctx, &initializer.token_a).await, 997_686);
+    assert_eq!(balance(&mut ctx, &initializer.token_b).await, 4_009_527);
+    assert!(ctx
+        .banks_client
+        .get_account(pool.vault_a)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(ctx
+        .banks_client
+        .get_account(pool.vault_b)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(ctx
+        .banks_client
+        .get_account(pool.amm)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_init_failures() {
+    let (mut ctx, program_id) = start().await;
+    let payer = ctx.payer.pubkey();
+
+    let err = create_pool(&mut ctx, &program_id, &payer, 3, 0, 1_000, 1_000)
+        .await
+        .unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InsufficientLiquidity));
+
+    let err = create_pool(
+        &mut ctx,
+        &program_id,
+        &payer,
+        1_000,
+        0,
+        1_000_000,
+        4_000_000,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidFee));
+
+    // An LP mint that can freeze providers' LP tokens could keep them from claiming their share
+    let curve = Curve::new(CurveType::ConstantProduct);
+    let err = init_pool(
+        &mut ctx,
+        &program_id,
+        &payer,
+        3,
+        0,
+        1_000_000,
+        4_000_000,
+        curve,
+        Some(&payer),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidFreezeAuthority));
+}
+
+#[tokio::test]
+async fn test_swap_failures() {
+    let (mut ctx, program_id) = start().await;
+    let payer = ctx.payer.pubkey();
+    let (pool, _) = create_pool(&mut ctx, &program_id, &payer, 3, 0, 1_000_000, 4_000_000)
+        .await
+        .unwrap();
+    let trader = create_user(&mut ctx, &pool, 10_000, 0).await;
+
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        39_487,
+    );
+    let err = send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::ExceededSlippage));
+
+    let mut ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        1,
+    );
+    ix.accounts[0].is_signer = false;
+    let err = send(&mut ctx, &[ix], &[]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::MissingSignature));
+
+    let mut ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        2,
+    );
+    ix.accounts[6].pubkey = Pubkey::new_unique();
+    let err = send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::IncorrectTokenProgramId));
+
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_a,
+        10_000,
+        3,
+    );
+    let err = send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::DuplicateAccounts));
+
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.lp,
+        10_000,
+        4,
+    );
+    let err = send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidMint));
+
+    assert_eq!(balance(&mut ctx, &trader.token_a).await, 10_000);
+    assert_eq!(balance(&mut ctx, &trader.token_b).await, 0);
+    assert_eq!(balance(&mut ctx, &pool.vault_a).await, 1_000_000);
+    assert_eq!(balance(&mut ctx, &pool.vault_b).await, 4_000_000);
+}
+
+#[tokio::test]
+async fn test_close_with_outstanding_liquidity() {
+    let (mut ctx, program_id) = start().await;
+    let payer = ctx.payer.pubkey();
+    let (pool, initializer) =
+        create_pool(&mut ctx, &program_id, &payer, 3, 0, 1_000_000, 4_000_000)
+            .await
+            .unwrap();
+
+    let ix = close_amm(&program_id, &pool, &initializer);
+    let err = send(&mut ctx, &[ix], &[&initializer.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::PoolNotEmpty));
+    assert_eq!(balance(&mut ctx, &pool.vault_a).await, 1_000_000);
+    assert_eq!(balance(&mut ctx, &pool.vault_b).await, 4_000_000);
+}
+
+#[tokio::test]
+async fn test_withdraw_protocol_fees() {
+    let (mut ctx, program_id) = start().await;
+    let fee_authority = Keypair::new();
+    let (pool, _) = create_pool(
+        &mut ctx,
+        &program_id,
+        &fee_authority.pubkey(),
+        3,
+        2,
+        1_000_000,
+        4_000_000,
+    )
+    .await
+    .unwrap();
+
+    let trader = create_user(&mut ctx, &pool, 10_000, 0).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        39_407,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &trader.token_b).await, 39_407);
+    assert_eq!(balance(&mut ctx, &pool.vault_a).await, 1_010_000);
+    assert_eq!(balance(&mut ctx, &pool.vault_b).await, 3_960_593);
+    let state = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(state.protocol_fees_a, 20);
+    assert_eq!(state.protocol_fees_b, 0);
+
+    let ix = instruction::withdraw_protocol_fees(
+        &program_id,
+        &trader.keypair.pubkey(),
+        &trader.token_a,
+        &trader.token_b,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.amm,
+    )
+    .unwrap();
+    let err = send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidFeeAuthority));
+
+    let fee_a = create_token_account(&mut ctx, &pool.mint_a, &fee_authority.pubkey()).await;
+    let fee_b = create_token_account(&mut ctx, &pool.mint_b, &fee_authority.pubkey()).await;
+    let ix = instruction::withdraw_protocol_fees(
+        &program_id,
+        &fee_authority.pubkey(),
+        &fee_a,
+        &fee_b,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.amm,
+    )
+    .unwrap();
+    send(&mut ctx, &[ix], &[&fee_authority]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &fee_a).await, 20);
+    assert_eq!(balance(&mut ctx, &fee_b).await, 0);
+    assert_eq!(balance(&mut ctx, &pool.vault_a).await, 1_009_980);
+    let state = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(state.protocol_fees_a, 0);
+}
+
+#[tokio::test]
+async fn test_donations_sync_and_skim() {
+    let (mut ctx, program_id) = start().await;
+    let payer = ctx.payer.pubkey();
+    let (pool, _) = create_pool(&mut ctx, &program_id, &payer, 3, 0, 1_000_000, 4_000_000)
+        .await
+        .unwrap();
+
+    mint_to(&mut ctx, &pool.mint_a, &pool.vault_a, 500_000).await;
+    let state = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(state.reserve_a, 1_000_000);
+    assert_eq!(state.reserve_b, 4_000_000);
+
+    let trader = create_user(&mut ctx, &pool, 10_000, 0).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        39_486,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &trader.token_b).await, 39_486);
+    let state = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(state.reserve_a, 1_010_000);
+    assert_eq!(state.reserve_b, 3_960_514);
+
+    let skimmer = create_user(&mut ctx, &pool, 0, 0).await;
+    let ix = instruction::skim(
+        &program_id,
+        &skimmer.token_a,
+        &skimmer.token_b,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.amm,
+    )
+    .unwrap();
+    send(&mut ctx, &[ix], &[]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &skimmer.token_a).await, 500_000);
+    assert_eq!(balance(&mut ctx, &skimmer.token_b).await, 0);
+    assert_eq!(balance(&mut ctx, &pool.vault_a).await, 1_010_000);
+    assert_eq!(balance(&mut ctx, &pool.vault_b).await, 3_960_514);
+
+    mint_to(&mut ctx, &pool.mint_b, &pool.vault_b, 39_486).await;
+    let ix = instruction::sync(&program_id, &pool.vault_a, &pool.vault_b, &pool.amm).unwrap();
+    send(&mut ctx, &[ix], &[]).await.unwrap();
+    let state = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(state.reserve_a, 1_010_000);
+    assert_eq!(state.reserve_b, 4_000_000);
+}
+
+#[tokio::test]
+async fn test_price_accumulators() {
+    let (mut ctx, program_id) = start().await;
+    let payer = ctx.payer.pubkey();
+    let (pool, _) = create_pool(&mut ctx, &program_id, &payer, 3, 0, 1_000_000, 4_000_000)
+        .await
+        .unwrap();
+    let clock = ctx.banks_client.get_sysvar::<Clock>().await.unwrap();
+    let state = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(state.last_update_timestamp, clock.unix_timestamp);
+    assert_eq!(state.price_a_cumulative, 0);
+    assert_eq!(state.price_b_cumulative, 0);
+
+    let now = advance_clock(&mut ctx, 100).await;
+    let trader = create_user(&mut ctx, &pool, 10_000, 0).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        39_486,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    let state = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(state.last_update_timestamp, now);
+    assert_eq!(state.price_a_cumulative, (4u128 << 64) * 100);
+    assert_eq!(state.price_b_cumulative, (1u128 << 62) * 100);
+
+    let now = advance_clock(&mut ctx, 50).await;
+    let ix = instruction::sync(&program_id, &pool.vault_a, &pool.vault_b, &pool.amm).unwrap();
+    send(&mut ctx, &[ix], &[]).await.unwrap();
+    let state = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(state.last_update_timestamp, now);
+    assert_eq!(
+        state.price_a_cumulative,
+        (4u128 << 64) * 100 + ((3_960_514u128 << 64) / 1_010_000) * 50
+    );
+    assert_eq!(
+        state.price_b_cumulative,
+        (1u128 << 62) * 100 + ((1_010_000u128 << 64) / 3_960_514) * 50
+    );
+}
+
+#[tokio::test]
+async fn test_twap_observations() {
+    let (mut ctx, program_id) = start().await;
+    let payer = ctx.payer.pubkey();
+    let (pool, _) = create_pool(&mut ctx, &program_id, &payer, 3, 0, 1_000_000, 4_000_000)
+        .await
+        .unwrap();
+    let start = ctx
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp;
+
+    let ix = instruction::init_observations(&program_id, &payer, &pool.amm).unwrap();
+    send(&mut ctx, &[ix], &[]).await.unwrap();
+    let observations = observations_state(&mut ctx, &program_id, &pool.amm).await;
+    assert_eq!(observations.amm_pubkey, pool.amm);
+    assert_eq!(observations.count, 1);
+    assert_eq!(observations.observations[0].timestamp, start);
+
+    advance_clock(&mut ctx, 100).await;
+    let trader = create_user(&mut ctx, &pool, 10_000, 0).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        39_486,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    let ix = instruction::sync(&program_id, &pool.vault_a, &pool.vault_b, &pool.amm).unwrap();
+    send(&mut ctx, &[ix], &[]).await.unwrap();
+    let observations = observations_state(&mut ctx, &program_id, &pool.amm).await;
+    assert_eq!(observations.count, 2);
+    assert_eq!(observations.observations[1].timestamp, start + 100);
+    assert_eq!(
+        observations.observations[1].price_a_cumulative,
+        (4u128 << 64) * 100
+    );
+
+    advance_clock(&mut ctx, 100).await;
+    let ix = instruction::sync(&program_id, &pool.vault_a, &pool.vault_b, &pool.amm).unwrap();
+    send(&mut ctx, &[ix], &[]).await.unwrap();
+    let observations = observations_state(&mut ctx, &program_id, &pool.amm).await;
+    assert_eq!(observations.count, 3);
+    assert_eq!(observations.observations[2].timestamp, start + 200);
+
+    // Both windows start between two observations, 50 seconds after the previous one
+    let price_a_after_swap = (3_960_514u128 << 64) / 1_010_000;
+    let price_b_after_swap = (1_010_000u128 << 64) / 3_960_514;
+    let (price_a, price_b) = query_twap(&mut ctx, &program_id, &pool.amm, 50)
+        .await
+        .unwrap();
+    assert_eq!(price_a, price_a_after_swap);
+    assert_eq!(price_b, price_b_after_swap);
+
+    let (price_a, price_b) = query_twap(&mut ctx, &program_id, &pool.amm, 150)
+        .await
+        .unwrap();
+    assert_eq!(
+        price_a,
+        ((4u128 << 64) * 50 + price_a_after_swap * 100) / 150
+    );
+    assert_eq!(
+        price_b,
+        ((1u128 << 62) * 50 + price_b_after_swap * 100) / 150
+    );
+
+    let err = query_twap(&mut ctx, &program_id, &pool.amm, 300)
+        .await
+        .unwrap_err();
+    assert_eq!(err, amm_error(AMMError::InsufficientObservations));
+
+    // Past the newest observation, the window is interpolated up to the pool's current cumulative prices
+    advance_clock(&mut ctx, 100).await;
+    let (price_a, price_b) = query_twap(&mut ctx, &program_id, &pool.amm, 50)
+        .await
+        .unwrap();
+    assert_eq!(price_a, price_a_after_swap);
+    assert_eq!(price_b, price_b_after_swap);
+}
+
+#[tokio::test]
+async fn test_quote_matches_swap() {
+    let (mut ctx, program_id) = start().await;
+    let payer = ctx.payer.pubkey();
+    let (pool, _) = create_pool(&mut ctx, &program_id, &payer, 3, 0, 1_000_000, 4_000_000)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        quote(&mut ctx, &program_id, &pool.amm, 10_000, true).await,
+        (39_486, 30, 99)
+    );
+    let trader = create_user(&mut ctx, &pool, 10_000, 50_000).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        0,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &trader.token_b).await, 89_486);
+
+    let (amount_out, fee, _) = quote(&mut ctx, &program_id, &pool.amm, 50_000, false).await;
+    assert_eq!((amount_out, fee), (12_554, 150));
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_b,
+        &trader.token_a,
+        50_000,
+        0,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &trader.token_a).await, 12_554);
+}
+
+#[tokio::test]
+async fn test_stable_swap_pool() {
+    let (mut ctx, program_id) = start().await;
+    let payer = ctx.payer.pubkey();
+
+    for (curve_type, amplification) in [
+        (CurveType::StableSwap, 0),
+        (CurveType::ConstantProduct, 100),
+    ] {
+        let err = create_stable_pool(&mut ctx, &program_id, &payer, curve_type, amplification)
+            .await
+            .unwrap_err();
+        assert_eq!(err.unwrap(), amm_error(AMMError::InvalidAmplification));
+    }
+
+    let (pool, initializer) =
+        create_stable_pool(&mut ctx, &program_id, &payer, CurveType::StableSwap, 100)
+            .await
+            .unwrap();
+    let amm_info = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(amm_info.curve_type, CurveType::StableSwap);
+    assert_eq!(amm_info.amplification, 100);
+    assert_eq!(balance(&mut ctx, &initializer.lp).await, 1_999_000);
+
+    // Far less slippage than the 9_871 a constant-product pool of the same depth would pay out
+    let (amount_out, fee, _) = quote(&mut ctx, &program_id, &pool.amm, 10_000, true).await;
+    assert_eq!((amount_out, fee), (9_969, 30));
+    let trader = create_user(&mut ctx, &pool, 10_000, 0).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        amount_out,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &trader.token_b).await, amount_out);
+
+    let ix = instruction::swap_exact_out(
+        &program_id,
+        &trader.keypair.pubkey(),
+        &trader.token_b,
+        &trader.token_a,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.amm,
+        5_000,
+        amount_out,
+        None,
+    )
+    .unwrap();
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &trader.token_a).await, 5_000);
+    assert_eq!(balance(&mut ctx, &trader.token_b).await, 4_953);
+
+    let amm_info = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(amm_info.reserve_a, balance(&mut ctx, &pool.vault_a).await);
+    assert_eq!(amm_info.reserve_b, balance(&mut ctx, &pool.vault_b).await);
+    assert!(
+        amm_info
+            .invariant(amm_info.reserve_a, amm_info.reserve_b)
+            .unwrap()
+            >= 2_000_000
+    );
+
+    let ix = claim_liquidity(&program_id, &pool, &initializer, 1_999_000);
+    send(&mut ctx, &[ix], &[&initializer.keypair])
+        .await
+        .unwrap();
+    assert_eq!(balance(&mut ctx, &initializer.lp).await, 0);
+}
+
+#[tokio::test]
+async fn test_ramp_amplification() {
+    let (mut ctx, program_id) = start().await;
+    let payer = ctx.payer.pubkey();
+    let (pool, initializer) =
+        create_stable_pool(&mut ctx, &program_id, &payer, CurveType::StableSwap, 100)
+            .await
+            .unwrap();
+    let initializer_key = initializer.keypair.pubkey();
+    let start = ctx
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp;
+
+    let ix = instruction::ramp_amplification(
+        &program_id,
+        &initializer_key,
+        &pool.amm,
+        1_000,
+        start + 3_600,
+    )
+    .unwrap();
+    let err = send(&mut ctx, &[ix], &[&initializer.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidRamp));
+
+    let ix = instruction::ramp_amplification(
+        &program_id,
+        &initializer_key,
+        &pool.amm,
+        1_001,
+        start + 172_800,
+    )
+    .unwrap();
+    let err = send(&mut ctx, &[ix], &[&initializer.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidAmplification));
+
+    let trader = create_user(&mut ctx, &pool, 30_000, 0).await;
+    let ix = instruction::ramp_amplification(
+        &program_id,
+        &trader.keypair.pubkey(),
+        &pool.amm,
+        1_000,
+        start + 172_800,
+    )
+    .unwrap();
+    let err = send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidInitializer));
+
+    let ix = instruction::ramp_amplification(
+        &program_id,
+        &initializer_key,
+        &pool.amm,
+        1_000,
+        start + 172_800,
+    )
+    .unwrap();
+    send(&mut ctx, &[ix], &[&initializer.keypair])
+        .await
+        .unwrap();
+    let state = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(state.amplification, 100);
+    assert_eq!(state.amplification_at(start + 86_400), 550);
+
+    // Trades price against the amplification interpolated at the time of the trade
+    advance_clock(&mut ctx, 86_400).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        0,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    assert_eq!(amm_state(&mut ctx, &pool.amm).await.amplification, 550);
+
+    let ix = instruction::stop_ramp(&program_id, &initializer_key, &pool.amm).unwrap();
+    send(&mut ctx, &[ix], &[&initializer.keypair])
+        .await
+        .unwrap();
+    advance_clock(&mut ctx, 86_400).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        0,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    let state = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(state.amplification, 550);
+    assert_eq!(state.target_amplification, 550);
+
+    // Only StableSwap pools have an amplification coefficient to freeze
+    let (pool, initializer) =
+        create_pool(&mut ctx, &program_id, &payer, 3, 0, 1_000_000, 1_000_000)
+            .await
+            .unwrap();
+    let ix = instruction::stop_ramp(&program_id, &initializer.keypair.pubkey(), &pool.amm).unwrap();
+    let err = send(&mut ctx, &[ix], &[&initializer.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidAmplification));
+}
+
+#[tokio::test]
+async fn test_weighted_pool() {
+    let (mut ctx, program_id) = start().await;
+    let payer = ctx.payer.pubkey();
+
+    for (curve_type, weight_a) in [
+        (CurveType::Weighted, 990_000),
+        (CurveType::ConstantProduct, 800_000),
+    ] {
+        let curve = Curve {
+            weight_a,
+            ..Curve::new(curve_type)
+        };
+        let err = create_curve_pool(&mut ctx, &program_id, &payer, 3, 0, 800_000, 200_000, curve)
+            .await
+            .unwrap_err();
+        assert_eq!(err.unwrap(), amm_error(AMMError::InvalidWeight));
+    }
+
+    // An 80/20 pool pricing token A at 1 token B
+    let curve = Curve {
+        weight_a: 800_000,
+        ..Curve::new(CurveType::Weighted)
+    };
+    let (pool, initializer) =
+        create_curve_pool(&mut ctx, &program_id, &payer, 3, 0, 800_000, 200_000, curve)
+            .await
+            .unwrap();
+    let amm_info = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!((amm_info.weight_a, amm_info.weight_b), (800_000, 200_000));
+    // 800,000^0.8 * 200,000^0.2 = 606,286.6
+    assert_eq!(balance(&mut ctx, &initializer.lp).await, 605_286);
+
+    // 200,000 * (1 - (800,000 / 809,970)^4) = 9,666.9
+    assert_eq!(
+        quote(&mut ctx, &program_id, &pool.amm, 10_000, true).await,
+        (9_666, 30, 305)
+    );
+    let trader = create_user(&mut ctx, &pool, 10_000, 0).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        9_666,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &trader.token_b).await, 9_666);
+
+    // 190,334 * ((810,000 / 805,000)^4 - 1) = 4,773.0, plus the fee
+    let ix = instruction::swap_exact_out(
+        &program_id,
+        &trader.keypair.pubkey(),
+        &trader.token_b,
+        &trader.token_a,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.amm,
+        5_000,
+        4_789,
+        None,
+    )
+    .unwrap();
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &trader.token_a).await, 5_000);
+    assert_eq!(balance(&mut ctx, &trader.token_b).await, 4_877);
+
+    // Deposits stay pro-rata to the reserves rather than to the weights
+    let amm_info = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!((amm_info.reserve_a, amm_info.reserve_b), (805_000, 195_123));
+    let provider = create_user(&mut ctx, &pool, 805_000, 195_123).await;
+    let ix = provide_liquidity(&program_id, &pool, &provider, 805_000, 195_123);
+    send(&mut ctx, &[ix], &[&provider.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &provider.lp).await, 606_286);
+    assert_eq!(balance(&mut ctx, &provider.token_a).await, 0);
+    assert_eq!(balance(&mut ctx, &provider.token_b).await, 0);
+}
+
+#[tokio::test]
+async fn test_liquidity_bootstrapping_pool() {
+    let (mut ctx, program_id) = start().await;
+    let payer = ctx.payer.pubkey();
+    let start = ctx
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp;
+    // A 95/5 to 50/50 sale of token A starting at a price of 1 token B
+    let curve = Curve {
+        weight_a: 950_000,
+        end_weight_a: 500_000,
+        start_timestamp: start + 100,
+        end_timestamp: start + 1_100,
+        ..Curve::new(CurveType::LiquidityBootstrapping)
+    };
+
+    let err = create_curve_pool(
+        &mut ctx,
+        &program_id,
+        &payer,
+        3,
+        0,
+        950_000,
+        50_000,
+        Curve {
+            end_timestamp: start + 100,
+            ..curve
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidRamp));
+
+    let (pool, initializer) =
+        create_curve_pool(&mut ctx, &program_id, &payer, 3, 0, 950_000, 50_000, curve)
+            .await
+            .unwrap();
+    // 950,000^0.95 * 50,000^0.05 = 819,947.3
+    let lp_amount = balance(&mut ctx, &initializer.lp).await;
+    assert_eq!(lp_amount, 818_947);
+    let trader = create_user(&mut ctx, &pool, 0, 2_000).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_b,
+        &trader.token_a,
+        1_000,
+        0,
+    );
+    let err = send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::SaleNotActive));
+    let ix = claim_liquidity(&program_id, &pool, &initializer, lp_amount);
+    let err = send(&mut ctx, &[ix], &[&initializer.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::SaleNotEnded));
+
+    // Halfway through the sale the pool weighs token A 72.5%
+    advance_clock(&mut ctx, 600).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_b,
+        &trader.token_a,
+        1_000,
+        0,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    let amm_info = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!((amm_info.weight_a, amm_info.weight_b), (725_000, 275_000));
+    // 950,000 * (1 - (50,000 / 50,997)^(0.275 / 0.725)) = 7,088.0
+    assert_eq!(balance(&mut ctx, &trader.token_a).await, 7_088);
+
+    advance_clock(&mut ctx, 600).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_b,
+        &trader.token_a,
+        1_000,
+        0,
+    );
+    let err = send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::SaleNotActive));
+
+    let ix = claim_liquidity(&program_id, &pool, &initializer, lp_amount);
+    send(&mut ctx, &[ix], &[&initializer.keypair])
+        .await
+        .unwrap();
+    assert_eq!(balance(&mut ctx, &initializer.lp).await, 0);
+    assert_eq!(balance(&mut ctx, &initializer.token_a).await, 941_762);
+    assert_eq!(balance(&mut ctx, &initializer.token_b).await, 50_937);
+}
+
+#[tokio::test]
+async fn test_concentrated_liquidity_pool() {
+    let (mut ctx, program_id) = start().await;
+    let payer = ctx.payer.pubkey();
+    let curve = Curve {
+        tick_spacing: 10,
+        ..Curve::new(CurveType::Concentrated)
+    };
+
+    let err = create_curve_pool(
+        &mut ctx,
+        &program_id,
+        &payer,
+        3,
+        0,
+        1,
+        1,
+        Curve {
+            tick_spacing: 0,
+            ..curve
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidTickSpacing));
+
+    // Nothing is deposited, the initial amounts only set the price to 1
+    let (pool, initializer) = create_curve_pool(&mut ctx, &program_id, &payer, 3, 0, 1, 1, curve)
+        .await
+        .unwrap();
+    assert_eq!(balance(&mut ctx, &initializer.token_a).await, 1);
+    let amm_info = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!((amm_info.sqrt_price, amm_info.tick_current), (1 << 64, 0));
+    let ix = instruction::init_ticks(&program_id, &payer, &pool.amm).unwrap();
+    send(&mut ctx, &[ix], &[]).await.unwrap();
+
+    let ix = provide_liquidity(&program_id, &pool, &initializer, 1, 1);
+    let err = send(&mut ctx, &[ix], &[&initializer.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidCurveType));
+    let trader = create_user(&mut ctx, &pool, 0, 10_000_000).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_b,
+        &trader.token_a,
+        1_000,
+        0,
+    );
+    let err = send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InsufficientLiquidity));
+
+    // One position around the price and one above it, holding token A only
+    let lp_1 = create_user(&mut ctx, &pool, 10_000_000, 10_000_000).await;
+    let lp_2 = create_user(&mut ctx, &pool, 10_000_000, 10_000_000).await;
+    for lp in [&lp_1, &lp_2] {
+        let fund_lp = system_instruction::transfer(&payer, &lp.keypair.pubkey(), 1_000_000_000);
+        send(&mut ctx, &[fund_lp], &[]).await.unwrap();
+    }
+    let ix = increase_liquidity(&program_id, &pool, &lp_1, -100, 105, 1_000_000_000);
+    let err = send(&mut ctx, &[ix], &[&lp_1.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidTickRange));
+    let ix = increase_liquidity(&program_id, &pool, &lp_1, -100, 100, 1_000_000_000);
+    send(&mut ctx, &[ix], &[&lp_1.keypair]).await.unwrap();
+    let ix = increase_liquidity(&program_id, &pool, &lp_2, 100, 300, 400_000_000);
+    send(&mut ctx, &[ix], &[&lp_2.keypair]).await.unwrap();
+    // 1,000,000,000 * (1 - 1.0001^-50) = 4,987,272.1 of each token
+    assert_eq!(
+        balance(&mut ctx, &lp_1.token_a).await,
+        10_000_000 - 4_987_273
+    );
+    assert_eq!(
+        balance(&mut ctx, &lp_1.token_b).await,
+        10_000_000 - 4_987_273
+    );
+    // 400,000,000 * (1.0001^-50 - 1.0001^-150) = 3,960,019.8 of token A only
+    assert_eq!(
+        balance(&mut ctx, &lp_2.token_a).await,
+        10_000_000 - 3_960_020
+    );
+    assert_eq!(balance(&mut ctx, &lp_2.token_b).await, 10_000_000);
+
+    // Buying all of the first position's token A crosses tick 100 into the second position
+    assert_eq!(
+        quote(&mut ctx, &program_id, &pool.amm, 6_000_000, false).await,
+        (5_945_042, 18_001, 62)
+    );
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_b,
+        &trader.token_a,
+        6_000_000,
+        0,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &trader.token_a).await, 5_945_042);
+    let amm_info = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(
+        (amm_info.tick_current, amm_info.liquidity),
+        (148, 400_000_000)
+    );
+    assert_eq!(
+        (amm_info.reserve_a, amm_info.reserve_b),
+        (3_002_251, 10_987_273)
+    );
+
+    // The fees owed to the positions sit in the vaults above the reserves
+    let skimmer = create_user(&mut ctx, &pool, 0, 0).await;
+    let ix = instruction::skim(
+        &program_id,
+        &skimmer.token_a,
+        &skimmer.token_b,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.amm,
+    )
+    .unwrap();
+    let err = send(&mut ctx, &[ix], &[]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidCurveType));
+
+    // Each position earned the fee on the part of the trade within its range
+    let ix = collect_fees(&program_id, &pool, &lp_1, -100, 100);
+    send(&mut ctx, &[ix], &[&lp_1.keypair]).await.unwrap();
+    assert_eq!(
+        balance(&mut ctx, &lp_1.token_b).await,
+        10_000_000 - 4_987_273 + 15_082
+    );
+    let ix = collect_fees(&program_id, &pool, &lp_2, 100, 300);
+    send(&mut ctx, &[ix], &[&lp_2.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &lp_2.token_b).await, 10_000_000 + 2_917);
+
+    // The first position is now entirely token B: 1,000,000,000 * (1.0001^50 - 1.0001^-50) = 9,999,541.7
+    let ix = instruction::decrease_liquidity(
+        &program_id,
+        &lp_1.keypair.pubkey(),
+        &lp_1.token_a,
+        &lp_1.token_b,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.amm,
+        -100,
+        100,
+        1_000_000_000,
+        0,
+        9_999_542,
+    )
+    .unwrap();
+    let err = send(&mut ctx, &[ix], &[&lp_1.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::ExceededSlippage));
+    let ix = instruction::decrease_liquidity(
+        &program_id,
+        &lp_1.keypair.pubkey(),
+        &lp_1.token_a,
+        &lp_1.token_b,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.amm,
+        -100,
+        100,
+        1_000_000_000,
+        0,
+        9_999_541,
+    )
+    .unwrap();
+    send(&mut ctx, &[ix], &[&lp_1.keypair]).await.unwrap();
+    assert_eq!(
+        balance(&mut ctx, &lp_1.token_a).await,
+        10_000_000 - 4_987_273
+    );
+    assert_eq!(
+        balance(&mut ctx, &lp_1.token_b).await,
+        10_000_000 - 4_987_273 + 15_082 + 9_999_541
+    );
+    let position = position_state(&mut ctx, &program_id, &pool, &lp_1, -100, 100).await;
+    assert_eq!(
+        (
+            position.liquidity,
+            position.fees_owed_a,
+            position.fees_owed_b
+        ),
+        (0, 0, 0)
+    );
+
+    // What is left in the pool backs the second position
+    let amm_info = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(
+        (amm_info.reserve_a, amm_info.reserve_b),
+        (3_002_251, 969_733)
+    );
+    assert_eq!(balance(&mut ctx, &pool.vault_a).await, amm_info.reserve_a);
+    assert_eq!(balance(&mut ctx, &pool.vault_b).await, amm_info.reserve_b);
+}
+
+#[tokio::test]
+async fn test_proactive_market_maker_pool() {
+    let (mut ctx, program_id) = start().await;
+    let payer = ctx.payer.pubkey();
+    let oracle = create_account(&mut ctx, Oracle::LEN, &program_id)
+        .await
+        .pubkey();
+    let ix = instruction::init_oracle(&program_id, &payer, &oracle, 2 << 64).unwrap();
+    send(&mut ctx, &[ix], &[]).await.unwrap();
+    let curve = Curve {
+        slippage_factor: 100_000,
+        max_oracle_staleness: 60,
+        oracle: Some(oracle),
+        ..Curve::new(CurveType::ProactiveMarketMaker)
+    };
+
+    let err = create_curve_pool(
+        &mut ctx,
+        &program_id,
+        &payer,
+        3,
+        0,
+        1_000_000,
+        2_000_000,
+        Curve {
+            slippage_factor: pmm::SLIPPAGE_FACTOR_ONE + 1,
+            ..curve
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidSlippageFactor));
+
+    let (pool, _initializer) = create_curve_pool(
+        &mut ctx,
+        &program_id,
+        &payer,
+        3,
+        0,
+        1_000_000,
+        2_000_000,
+        curve,
+    )
+    .await
+    .unwrap();
+    let amm_info = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(
+        (amm_info.target_a, amm_info.target_b),
+        (1_000_000, 2_000_000)
+    );
+
+    // At its targets the pool trades at the oracle price of 2, slipping as token B runs short:
+    // 9,970 after the fee is worth 19,940 and d * (0.9 + 0.1 * 2,000,000 / (2,000,000 - d)) <= 19,940
+    let trader = create_user(&mut ctx, &pool, 20_000, 0).await;
+    let (quoted_amount_out, _, _) = quote(&mut ctx, &program_id, &pool.amm, 10_000, true).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        quoted_amount_out,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    assert_eq!(quoted_amount_out, 19_919);
+    assert_eq!(balance(&mut ctx, &trader.token_b).await, 19_919);
+
+    // Selling token B back earns more than the oracle price of the 4,985 left after the fee
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_b,
+        &trader.token_a,
+        5_000,
+        2_493,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &trader.token_a).await, 12_496);
+
+    // The curve follows the oracle: at a price of 3, the 7,504 token A above its target are worth the
+    // 22,512 token B the pool is short of, which moves the target of token B up to 2,007,568
+    let ix = instruction::update_oracle(&program_id, &payer, &oracle, 3 << 64).unwrap();
+    send(&mut ctx, &[ix], &[]).await.unwrap();
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        29_795,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    assert_eq!(balance(&mut ctx, &trader.token_b).await, 14_919 + 29_795);
+    let amm_info = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(
+        (amm_info.reserve_a, amm_info.reserve_b),
+        (1_017_504, 1_955_286)
+    );
+    assert_eq!(
+        (amm_info.target_a, amm_info.target_b),
+        (1_000_000, 2_007_568)
+    );
+
+    let outsider = Keypair::new();
+    let ix = instruction::update_oracle(&program_id, &outsider.pubkey(), &oracle, 1 << 64).unwrap();
+    let err = send(&mut ctx, &[ix], &[&outsider]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidOracleAuthority));
+
+    let ix = instruction::swap(
+        &program_id,
+        &trader.keypair.pubkey(),
+        &trader.token_a,
+        &trader.token_b,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.amm,
+        1_000,
+        0,
+        None,
+    )
+    .unwrap();
+    let err = send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidOracleAccount));
+
+    // Trades need a price at most 60 seconds old
+    advance_clock(&mut ctx, 61).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        1_000,
+        0,
+    );
+    let err = send(&mut ctx, std::slice::from_ref(&ix), &[&trader.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::StaleOraclePrice));
+    // So does seeding a new pool at the oracle price
+    let err = create_curve_pool(
+        &mut ctx,
+        &program_id,
+        &payer,
+        3,
+        0,
+        1_000_000,
+        2_000_000,
+        curve,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::StaleOraclePrice));
+    let update = instruction::update_oracle(&program_id, &payer, &oracle, 3 << 64).unwrap();
+    send(&mut ctx, &[update, ix], &[&trader.keypair])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_price_deviation_guard() {
+    let (mut ctx, program_id) = start().await;
+    let payer = ctx.payer.pubkey();
+    let oracle = create_account(&mut ctx, Oracle::LEN, &program_id)
+        .await
+        .pubkey();
+    let ix = instruction::init_oracle(&program_id, &payer, &oracle, 1 << 64).unwrap();
+    send(&mut ctx, &[ix], &[]).await.unwrap();
+    let curve = Curve {
+        max_oracle_staleness: 60,
+        max_price_deviation_bps: 100,
+        oracle: Some(oracle),
+        ..Curve::new(CurveType::ConstantProduct)
+    };
+
+    let err = create_curve_pool(
+        &mut ctx,
+        &program_id,
+        &payer,
+        3,
+        0,
+        1_000_000,
+        1_000_000,
+        Curve {
+            oracle: None,
+            ..curve
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidOracleAccount));
+
+    let (pool, _initializer) = create_curve_pool(
+        &mut ctx,
+        &program_id,
+        &payer,
+        3,
+        0,
+        1_000_000,
+        1_000_000,
+        curve,
+    )
+    .await
+    .unwrap();
+    let amm_info = amm_state(&mut ctx, &pool.amm).await;
+    assert_eq!(amm_info.oracle_pubkey, oracle);
+    assert_eq!(amm_info.max_price_deviation_bps, 100);
+
+    // 998,010 / 1,002,000 is 40 basis points below the oracle price of 1
+    let trader = create_user(&mut ctx, &pool, 20_000, 10_000).await;
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        2_000,
+        1_990,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+
+    // Another 10,000 token A would leave the pool 2.4% below it, so neither a swap nor its quote goes through
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        0,
+    );
+    let err = send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::PriceDeviationExceeded));
+    let ix = instruction::quote(
+        &program_id,
+        &pool.amm,
+        CurveType::ConstantProduct,
+        10_000,
+        true,
+        Some(&oracle),
+    )
+    .unwrap();
+    let err = return_data(&mut ctx, ix).await.unwrap_err();
+    assert_eq!(err, amm_error(AMMError::PriceDeviationExceeded));
+    let (amount_out, _, _) = quote(&mut ctx, &program_id, &pool.amm, 1_000, true).await;
+    assert!(amount_out > 0);
+    let ix = instruction::swap_exact_out(
+        &program_id,
+        &trader.keypair.pubkey(),
+        &trader.token_a,
+        &trader.token_b,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.amm,
+        5_000,
+        u64::MAX,
+        Some(&oracle),
+    )
+    .unwrap();
+    let err = send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::PriceDeviationExceeded));
+
+    // The guard follows the oracle
+    let ix =
+        instruction::update_oracle(&program_id, &payer, &oracle, (1 << 64) * 98 / 100).unwrap();
+    send(&mut ctx, &[ix], &[]).await.unwrap();
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_a,
+        &trader.token_b,
+        10_000,
+        0,
+    );
+    send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap();
+    let ix = swap(
+        &program_id,
+        &pool,
+        &trader,
+        &trader.token_b,
+        &trader.token_a,
+        10_000,
+        0,
+    );
+    let err = send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::PriceDeviationExceeded));
+
+    let ix = instruction::swap(
+        &program_id,
+        &trader.keypair.pubkey(),
+        &trader.token_b,
+        &trader.token_a,
+        &pool.vault_a,
+        &pool.vault_b,
+        &pool.amm,
+        1_000,
+        0,
+        None,
+    )
+    .unwrap();
+    let err = send(&mut ctx, &[ix], &[&trader.keypair]).await.unwrap_err();
+    assert_eq!(err.unwrap(), amm_error(AMMError::InvalidOracleAccount));
+}